@@ -0,0 +1,17 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+// The maximum sizes, in bytes, of each event's fields, and the maximum number of distinct event
+// names. Once the distinct names limit is reached, only events with existing names are accepted.
+// After upgrading from a version which didn't track distinct names, the distinct names limit isn't
+// enforced until the existing events have been backfilled.
+// Events exceeding any of these are rejected individually, the rest of their batch is still
+// stored. Limits set to `None` are unbounded.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventLimitsConfig {
+    pub max_payload_bytes: Option<u32>,
+    pub max_name_bytes: Option<u32>,
+    pub max_user_bytes: Option<u32>,
+    pub max_source_bytes: Option<u32>,
+    pub max_distinct_names: Option<u32>,
+}
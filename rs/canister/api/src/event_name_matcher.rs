@@ -0,0 +1,10 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+// Selects the events a setting applies to. When several match an event, an exact match takes
+// precedence, followed by the longest matching prefix.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum EventNameMatcher {
+    Exact(String),
+    Prefix(String),
+}
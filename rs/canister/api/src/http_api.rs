@@ -0,0 +1,11 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+// Controls access to the events exposed via `http_request` (eg. `/events?start=0&length=100`), and
+// separately to the `/metrics` endpoint. If `public` is false then requests must include one of
+// the `tokens` in an `Authorization: Bearer <token>` header.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventsHttpApiConfig {
+    pub public: bool,
+    pub tokens: Vec<String>,
+}
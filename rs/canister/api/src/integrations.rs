@@ -0,0 +1,11 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+// An event is counted as a DappRadar transaction if it has a user and its name either matches one
+// of `transaction_event_names` or starts with one of `transaction_event_name_prefixes`. If both
+// lists are empty, every event with a user is counted.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DappRadarConfig {
+    pub transaction_event_names: Vec<String>,
+    pub transaction_event_name_prefixes: Vec<String>,
+}
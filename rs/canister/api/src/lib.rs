@@ -1,9 +1,23 @@
+mod event_limits;
+mod event_name_matcher;
+mod http_api;
+mod integrations;
 mod lifecycle;
+mod producer_limits;
 mod queries;
+mod streams;
+mod timestamp_policy;
 mod updates;
 
+pub use event_limits::*;
+pub use event_name_matcher::*;
+pub use http_api::*;
+pub use integrations::*;
 pub use lifecycle::*;
+pub use producer_limits::*;
 pub use queries::*;
+pub use streams::*;
+pub use timestamp_policy::*;
 pub use updates::*;
 
 pub use event_store_types::{IdempotentEvent, IndexedEvent, Milliseconds};
@@ -1,4 +1,7 @@
-use crate::Milliseconds;
+use crate::{
+    DappRadarConfig, EventLimitsConfig, EventsHttpApiConfig, Milliseconds, ProducerLimitsConfig,
+    TimestampPolicy,
+};
 use candid::{CandidType, Deserialize, Principal};
 use serde::Serialize;
 
@@ -7,4 +10,10 @@ pub struct InitArgs {
     pub push_events_whitelist: Vec<Principal>,
     pub read_events_whitelist: Vec<Principal>,
     pub time_granularity: Option<Milliseconds>,
+    pub dapp_radar_config: Option<DappRadarConfig>,
+    pub events_http_api_config: Option<EventsHttpApiConfig>,
+    pub metrics_http_api_config: Option<EventsHttpApiConfig>,
+    pub producer_limits: Option<ProducerLimitsConfig>,
+    pub event_limits: Option<EventLimitsConfig>,
+    pub timestamp_policy: Option<TimestampPolicy>,
 }
@@ -1,3 +1,5 @@
 mod init;
+mod post_upgrade;
 
 pub use init::*;
+pub use post_upgrade::*;
@@ -0,0 +1,15 @@
+use crate::{
+    DappRadarConfig, EventLimitsConfig, EventsHttpApiConfig, ProducerLimitsConfig, TimestampPolicy,
+};
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct UpgradeArgs {
+    pub dapp_radar_config: Option<DappRadarConfig>,
+    pub events_http_api_config: Option<EventsHttpApiConfig>,
+    pub metrics_http_api_config: Option<EventsHttpApiConfig>,
+    pub producer_limits: Option<ProducerLimitsConfig>,
+    pub event_limits: Option<EventLimitsConfig>,
+    pub timestamp_policy: Option<TimestampPolicy>,
+}
@@ -0,0 +1,18 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+// Limits applied to each principal pushing events. Principals listed in `per_principal` use
+// those limits instead of the `default` limits. Batches exceeding the limits are rejected via
+// `PushEventsResponse`, which producers older than 0.11.0 treat as success, silently dropping the
+// batch. So limits must not be enabled until every producer has been upgraded.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProducerLimitsConfig {
+    pub default: ProducerLimits,
+    pub per_principal: Vec<(Principal, ProducerLimits)>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProducerLimits {
+    pub max_batch_size: Option<u32>,
+    pub max_events_per_minute: Option<u64>,
+}
@@ -6,6 +6,8 @@ use serde::{Deserialize, Serialize};
 pub struct EventsArgs {
     pub start: u64,
     pub length: u64,
+    // Reads from the default stream if not set
+    pub stream: Option<String>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
@@ -0,0 +1,15 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct IntegrationsStatusResponse {
+    pub latest_event_index: Option<u64>,
+    pub integrations: Vec<IntegrationStatus>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct IntegrationStatus {
+    pub name: String,
+    pub next_event_index: u64,
+    pub events_remaining: u64,
+}
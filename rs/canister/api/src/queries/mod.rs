@@ -1,5 +1,15 @@
 mod events;
+mod integrations_status;
+mod sample_rates;
+mod stats;
+mod streams;
+mod time_granularity;
 mod whitelisted_principals;
 
 pub use events::*;
+pub use integrations_status::*;
+pub use sample_rates::*;
+pub use stats::*;
+pub use streams::*;
+pub use time_granularity::*;
 pub use whitelisted_principals::*;
@@ -0,0 +1,14 @@
+use crate::EventNameMatcher;
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SampleRatesResponse {
+    pub sample_rates: Vec<SampleRate>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SampleRate {
+    pub event_name: EventNameMatcher,
+    pub sample_rate_per_million: u32,
+}
@@ -0,0 +1,47 @@
+use crate::IntegrationsStatusResponse;
+use candid::{CandidType, Principal};
+use event_store_types::{Milliseconds, TimestampMillis};
+use serde::{Deserialize, Serialize};
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct StatsResponse {
+    pub total_events: u64,
+    pub latest_event_index: Option<u64>,
+    pub events_per_name: Vec<EventNameStats>,
+    pub distinct_strings: u64,
+    pub distinct_names: u64,
+    pub distinct_users: u64,
+    pub distinct_sources: u64,
+    // Distinct value counts only include events up to this index until the backfill completes
+    pub distinct_values_backfilled_up_to: u64,
+    pub stable_memory_bytes: u64,
+    pub heap_memory_bytes: u64,
+    pub memory: Vec<MemoryStats>,
+    pub dedup_window_duration: Milliseconds,
+    pub dedup_keys: u64,
+    pub salt_initialized: bool,
+    pub integrations: IntegrationsStatusResponse,
+    pub producers: Vec<ProducerStats>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct EventNameStats {
+    pub name: String,
+    pub count: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct MemoryStats {
+    pub memory_id: u8,
+    pub name: String,
+    pub bytes: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct ProducerStats {
+    pub principal: Principal,
+    pub last_push: TimestampMillis,
+    pub events: u64,
+    pub bytes: u64,
+    pub rejected_batches: u64,
+}
@@ -0,0 +1,18 @@
+use crate::StreamConfig;
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct StreamsResponse {
+    pub streams: Vec<StreamSummary>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct StreamSummary {
+    pub name: String,
+    pub config: StreamConfig,
+    // The number of events currently retained
+    pub event_count: u64,
+    pub first_event_index: Option<u64>,
+    pub latest_event_index: Option<u64>,
+}
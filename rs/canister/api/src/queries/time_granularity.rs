@@ -0,0 +1,15 @@
+use crate::{EventNameMatcher, Milliseconds};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TimeGranularityResponse {
+    pub default: Option<Milliseconds>,
+    pub overrides: Vec<TimeGranularityOverride>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct TimeGranularityOverride {
+    pub event_name: EventNameMatcher,
+    pub time_granularity: Option<Milliseconds>,
+}
@@ -0,0 +1,21 @@
+use crate::Milliseconds;
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+// Each stream has its own index sequence and its own whitelists. Principals whitelisted for the
+// default stream have no access to other streams unless they are also listed here.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StreamConfig {
+    pub push_events_whitelist: Vec<Principal>,
+    pub read_events_whitelist: Vec<Principal>,
+    pub retention: StreamRetention,
+}
+
+// Events are removed from the start of the stream once they exceed either limit. Removing events
+// does not change the indexes of the remaining events.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StreamRetention {
+    // Based on when each event was ingested
+    pub max_age: Option<Milliseconds>,
+    pub max_events: Option<u64>,
+}
@@ -0,0 +1,37 @@
+use crate::Milliseconds;
+use candid::CandidType;
+use event_store_types::TimestampMillis;
+use serde::{Deserialize, Serialize};
+
+// Determines how the timestamps supplied by producers are handled. Each event's ingestion
+// timestamp is recorded regardless of the policy.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimestampPolicy {
+    // Store the producer's timestamp as is
+    #[default]
+    Keep,
+    // Clamp the producer's timestamp to within the given distance of the ingestion timestamp
+    Clamp(TimestampClamp),
+    // Replace the producer's timestamp with the ingestion timestamp
+    UseIngestionTime,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimestampClamp {
+    pub max_past: Option<Milliseconds>,
+    pub max_future: Option<Milliseconds>,
+}
+
+impl TimestampPolicy {
+    pub fn apply(&self, timestamp: TimestampMillis, now: TimestampMillis) -> TimestampMillis {
+        match self {
+            TimestampPolicy::Keep => timestamp,
+            TimestampPolicy::Clamp(clamp) => {
+                let min = clamp.max_past.map_or(0, |d| now.saturating_sub(d));
+                let max = clamp.max_future.map_or(u64::MAX, |d| now.saturating_add(d));
+                timestamp.clamp(min, max)
+            }
+            TimestampPolicy::UseIngestionTime => now,
+        }
+    }
+}
@@ -1,3 +1,13 @@
 mod push_events;
+mod remove_sample_rate;
+mod remove_time_granularity_override;
+mod set_sample_rate;
+mod set_stream;
+mod set_time_granularity_override;
 
 pub use push_events::*;
+pub use remove_sample_rate::*;
+pub use remove_time_granularity_override::*;
+pub use set_sample_rate::*;
+pub use set_stream::*;
+pub use set_time_granularity_override::*;
@@ -1,8 +1,63 @@
-use crate::IdempotentEvent;
+use crate::{IdempotentEvent, Milliseconds};
 use candid::{CandidType, Deserialize};
 use serde::Serialize;
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
 pub struct PushEventsArgs {
     pub events: Vec<IdempotentEvent>,
+    // Pushes to the default stream if not set
+    pub stream: Option<String>,
+}
+
+// Rejected batches should be retried later, none of their events will have been stored
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub enum PushEventsResponse {
+    Success,
+    // Some events were rejected, all others were stored
+    PartialSuccess(PartialSuccess),
+    RateLimitExceeded(RateLimitExceeded),
+    BatchTooLarge(BatchTooLarge),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct PartialSuccess {
+    pub rejected_events: Vec<RejectedEvent>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RejectedEvent {
+    // The event's position within the batch
+    pub index: u32,
+    pub idempotency_key: u128,
+    pub violations: Vec<EventViolation>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum EventViolation {
+    PayloadTooLarge(SizeLimit),
+    NameTooLong(SizeLimit),
+    UserTooLong(SizeLimit),
+    SourceTooLong(SizeLimit),
+    TooManyDistinctNames(CardinalityLimit),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SizeLimit {
+    pub size: u32,
+    pub max: u32,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CardinalityLimit {
+    pub max: u32,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RateLimitExceeded {
+    pub retry_after: Milliseconds,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct BatchTooLarge {
+    pub max_batch_size: u32,
 }
@@ -0,0 +1,8 @@
+use crate::EventNameMatcher;
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RemoveSampleRateArgs {
+    pub event_name: EventNameMatcher,
+}
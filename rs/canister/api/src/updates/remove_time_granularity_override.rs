@@ -0,0 +1,8 @@
+use crate::EventNameMatcher;
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct RemoveTimeGranularityOverrideArgs {
+    pub event_name: EventNameMatcher,
+}
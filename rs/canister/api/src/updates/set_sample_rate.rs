@@ -0,0 +1,11 @@
+use crate::EventNameMatcher;
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+// Only the given proportion of matching events (in parts per million) will be stored. Whether an
+// event is kept is determined by its idempotency key, so retries of an event are treated the same.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SetSampleRateArgs {
+    pub event_name: EventNameMatcher,
+    pub sample_rate_per_million: u32,
+}
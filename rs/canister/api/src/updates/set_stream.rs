@@ -0,0 +1,23 @@
+use crate::StreamConfig;
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+// Creates the stream if it doesn't exist, otherwise replaces its config
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SetStreamArgs {
+    pub name: String,
+    pub config: StreamConfig,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum SetStreamResponse {
+    Success,
+    // Names must be 1 to 64 characters, each alphanumeric, '_' or '-'
+    InvalidName,
+    TooManyStreams(TooManyStreams),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TooManyStreams {
+    pub max: u32,
+}
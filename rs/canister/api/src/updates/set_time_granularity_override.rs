@@ -0,0 +1,10 @@
+use crate::{EventNameMatcher, Milliseconds};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+// A `time_granularity` of `None` (or zero) means timestamps of matching events are stored as is
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug)]
+pub struct SetTimeGranularityOverrideArgs {
+    pub event_name: EventNameMatcher,
+    pub time_granularity: Option<Milliseconds>,
+}
@@ -1,6 +1,8 @@
 use candid::Principal;
 use event_store_types::TimestampMillis;
 
+const WASM_PAGE_SIZE_BYTES: u64 = 64 * 1024;
+
 pub fn time() -> TimestampMillis {
     ic_cdk::api::time() / 1_000_000
 }
@@ -8,3 +10,32 @@ pub fn time() -> TimestampMillis {
 pub fn caller() -> Principal {
     ic_cdk::api::msg_caller()
 }
+
+// Reads the size without copying the arg
+pub fn arg_data_size() -> u64 {
+    unsafe { ic0::msg_arg_data_size() as u64 }
+}
+
+pub fn cycles_balance() -> u128 {
+    ic_cdk::api::canister_cycle_balance()
+}
+
+pub fn stable_memory_bytes() -> u64 {
+    ic_cdk::stable::stable_size() * WASM_PAGE_SIZE_BYTES
+}
+
+pub fn heap_memory_bytes() -> u64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        core::arch::wasm32::memory_size(0) as u64 * WASM_PAGE_SIZE_BYTES
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        0
+    }
+}
+
+pub fn instruction_counter() -> u64 {
+    ic_cdk::api::instruction_counter()
+}
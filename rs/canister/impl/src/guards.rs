@@ -1,7 +1,12 @@
-use crate::state;
+use crate::{env, state};
+use candid::{CandidType, Deserialize};
 
+// Access is checked against the stream named in the args. Doing so within the guard means that
+// unauthorized calls are rejected (CanisterReject) rather than trapping (CanisterError), which
+// producers would treat as the events in the batch being at fault.
 pub fn caller_can_push_events() -> Result<(), String> {
-    if state::read(|s| s.can_caller_push_events()) {
+    let stream = stream_from_args();
+    if state::read(|s| s.can_caller_push_to_stream(stream.as_deref())) {
         Ok(())
     } else {
         Err(err_message("push"))
@@ -9,13 +14,36 @@ pub fn caller_can_push_events() -> Result<(), String> {
 }
 
 pub fn caller_can_read_events() -> Result<(), String> {
-    if state::read(|s| s.can_caller_read_events()) {
+    let stream = stream_from_args();
+    if state::read(|s| s.can_caller_read_from_stream(stream.as_deref())) {
         Ok(())
     } else {
         Err(err_message("read"))
     }
 }
 
+// Only the `stream` field is decoded, any other fields (eg. the events being pushed) are skipped
+#[derive(CandidType, Deserialize)]
+struct StreamArgs {
+    stream: Option<String>,
+}
+
+// Args which fail to decode are treated as targeting the default stream, the call then fails
+// when the endpoint decodes them
+fn stream_from_args() -> Option<String> {
+    candid::decode_one::<StreamArgs>(&ic_cdk::api::msg_arg_data())
+        .ok()
+        .and_then(|args| args.stream)
+}
+
 fn err_message(action: &'static str) -> String {
     format!("Caller is not authorized to {action} events")
 }
+
+pub fn caller_is_controller() -> Result<(), String> {
+    if ic_cdk::api::is_controller(&env::caller()) {
+        Ok(())
+    } else {
+        Err("Caller is not a controller".to_string())
+    }
+}
@@ -0,0 +1,142 @@
+use crate::http::{json_response, response_from_status_code};
+use crate::integrations::dapp_radar::{DappRadarView, DateKey};
+use crate::model::certified_responses::{CertifiableRoute, CertificationType};
+use crate::state::State;
+use ic_http_certification::{HttpRequest, HttpResponse};
+use std::str::FromStr;
+
+const MAX_AGGREGATED_DATA_RANGE_DAYS: i64 = 31;
+const MAX_SUMMARY_RANGE_DAYS: i64 = 92;
+
+pub fn process_request(
+    state: &State,
+    segments: &[&str],
+    qs: Option<&str>,
+) -> Option<HttpResponse<'static>> {
+    if segments.len() < 3 || segments[0] != "dapp-radar" {
+        return None;
+    }
+
+    let dapp_radar = &state.integrations_data().dapp_radar;
+
+    let data = match (segments[1], segments.len()) {
+        ("aggregated-data", 4 | 5) => {
+            let Some(page) = qs
+                .map(querystring::querify)
+                .unwrap_or_default()
+                .into_iter()
+                .find(|(k, _)| *k == "page")
+                .map_or(Some(0), |(_, v)| usize::from_str(v).ok())
+            else {
+                return Some(response_from_status_code(400));
+            };
+
+            let grouping = *segments.last().unwrap();
+
+            let response = if segments.len() == 4 {
+                let (year, month, day) = parse_date(segments[2])?;
+
+                if grouping == "daily" {
+                    dapp_radar.daily(year, month, day, page)
+                } else if grouping == "hourly" {
+                    dapp_radar.hourly(year, month, day, page)
+                } else {
+                    return None;
+                }
+            } else {
+                let (start, end) =
+                    parse_date_range(segments[2], segments[3], MAX_AGGREGATED_DATA_RANGE_DAYS)?;
+
+                if grouping == "daily" {
+                    dapp_radar.daily_range(start, end, page)
+                } else if grouping == "hourly" {
+                    dapp_radar.hourly_range(start, end, page)
+                } else {
+                    return None;
+                }
+            };
+
+            serde_json::to_vec(&response).unwrap()
+        }
+        ("summary", 3 | 4) => {
+            let (start, end) = if segments.len() == 3 {
+                let date = parse_date(segments[2])?;
+                (date, date)
+            } else {
+                parse_date_range(segments[2], segments[3], MAX_SUMMARY_RANGE_DAYS)?
+            };
+
+            serde_json::to_vec(&dapp_radar.summary(start, end)).unwrap()
+        }
+        _ => return None,
+    };
+
+    Some(json_response(data))
+}
+
+// The single day route of a view whose data is finalized. Each page of the aggregated data is
+// certified, along with the request without a `page` query parameter, which is equivalent to page 0
+pub fn certifiable_route(state: &State, date: DateKey, view: DappRadarView) -> CertifiableRoute {
+    let dapp_radar = &state.integrations_data().dapp_radar;
+    let (year, month, day) = date;
+    let path = certified_path(date, view);
+
+    let page_count = match view {
+        DappRadarView::Daily => dapp_radar.daily(year, month, day, 0).page_count(),
+        DappRadarView::Hourly => dapp_radar.hourly(year, month, day, 0).page_count(),
+        DappRadarView::Summary => {
+            return CertifiableRoute {
+                requests: vec![HttpRequest::get(path.clone()).build()],
+                path,
+                certification_type: CertificationType::ResponseOnly,
+            };
+        }
+    };
+
+    let mut requests = vec![HttpRequest::get(path.clone()).build()];
+    requests.extend((0..=page_count).map(|p| HttpRequest::get(format!("{path}?page={p}")).build()));
+
+    CertifiableRoute {
+        path,
+        certification_type: CertificationType::Full {
+            query_parameters: &["page"],
+        },
+        requests,
+    }
+}
+
+pub fn certified_path((year, month, day): DateKey, view: DappRadarView) -> String {
+    let date = format!("{year}-{month:02}-{day:02}");
+
+    match view {
+        DappRadarView::Daily => format!("/dapp-radar/aggregated-data/{date}/daily"),
+        DappRadarView::Hourly => format!("/dapp-radar/aggregated-data/{date}/hourly"),
+        DappRadarView::Summary => format!("/dapp-radar/summary/{date}"),
+    }
+}
+
+fn parse_date(date_str: &str) -> Option<DateKey> {
+    let date_parts: Vec<_> = date_str.split('-').collect();
+    if date_parts.len() != 3 {
+        return None;
+    }
+
+    let year = u32::from_str(date_parts[0]).ok()?;
+    let month = u8::from_str(date_parts[1]).ok()?;
+    let day = u8::from_str(date_parts[2]).ok()?;
+
+    Some((year, month, day))
+}
+
+fn parse_date_range(start: &str, end: &str, max_days: i64) -> Option<(DateKey, DateKey)> {
+    fn to_date((year, month, day): DateKey) -> Option<time::Date> {
+        let month = time::Month::try_from(month).ok()?;
+        time::Date::from_calendar_date(year as i32, month, day).ok()
+    }
+
+    let start = parse_date(start)?;
+    let end = parse_date(end)?;
+    let days = (to_date(end)? - to_date(start)?).whole_days() + 1;
+
+    (1..=max_days).contains(&days).then_some((start, end))
+}
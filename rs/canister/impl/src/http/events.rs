@@ -0,0 +1,124 @@
+use crate::http::{is_authorized, response_from_status_code};
+use crate::state::State;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use event_store_types::{IndexedEvent, TimestampMillis};
+use ic_http_certification::{HttpRequest, HttpResponse, HttpResponseBuilder};
+use serde::Serialize;
+use std::str::FromStr;
+
+const DEFAULT_LENGTH: u64 = 100;
+const MAX_LENGTH: u64 = 1000;
+const LATEST_EVENT_INDEX_HEADER: &str = "x-latest-event-index";
+
+// Serves `/events?start=&length=&name=&format=`. The `name` filter is applied to the events within
+// the requested range, so callers should page through using `start` and the latest event index
+// rather than the number of events returned.
+pub fn process_request(
+    state: &State,
+    request: &HttpRequest,
+    qs: Option<&str>,
+) -> HttpResponse<'static> {
+    let params = qs.map(querystring::querify).unwrap_or_default();
+    let param = |key: &str| params.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+
+    if !is_authorized(state.events_http_api(), request) {
+        return response_from_status_code(401);
+    }
+
+    let Some(start) = param("start").map_or(Some(0), |v| u64::from_str(v).ok()) else {
+        return response_from_status_code(400);
+    };
+    let Some(length) = param("length").map_or(Some(DEFAULT_LENGTH), |v| u64::from_str(v).ok())
+    else {
+        return response_from_status_code(400);
+    };
+    let name = param("name");
+
+    let events: Vec<_> = state
+        .events()
+        .get(start, length.min(MAX_LENGTH))
+        .into_iter()
+        .filter(|e| name.is_none_or(|n| e.name == n))
+        .map(HttpEvent::from)
+        .collect();
+
+    let latest_event_index = state.events().latest_event_index();
+
+    let (content_type, body) = if param("format") == Some("ndjson") {
+        let mut body = Vec::new();
+        for event in events {
+            serde_json::to_writer(&mut body, &event).unwrap();
+            body.push(b'\n');
+        }
+        ("application/x-ndjson", body)
+    } else {
+        let response = HttpEventsResponse {
+            events,
+            latest_event_index,
+        };
+        ("application/json", serde_json::to_vec(&response).unwrap())
+    };
+
+    let mut headers = vec![
+        ("content-type".to_string(), content_type.to_string()),
+        ("content-length".to_string(), body.len().to_string()),
+        ("cache-control".to_string(), "no-store".to_string()),
+    ];
+    if let Some(index) = latest_event_index {
+        headers.push((LATEST_EVENT_INDEX_HEADER.to_string(), index.to_string()));
+    }
+
+    HttpResponseBuilder::new()
+        .with_status_code(200.try_into().unwrap())
+        .with_headers(headers)
+        .with_body(body)
+        .build()
+}
+
+#[derive(Serialize)]
+struct HttpEventsResponse {
+    events: Vec<HttpEvent>,
+    latest_event_index: Option<u64>,
+}
+
+// Payloads which are valid JSON are inlined, all others are base64 encoded
+#[derive(Serialize)]
+struct HttpEvent {
+    index: u64,
+    name: String,
+    timestamp: TimestampMillis,
+    ingested_at: Option<TimestampMillis>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sample_rate_per_million: Option<u32>,
+    user: Option<String>,
+    source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload_base64: Option<String>,
+}
+
+impl From<IndexedEvent> for HttpEvent {
+    fn from(value: IndexedEvent) -> Self {
+        let (payload, payload_base64) = if value.payload.is_empty() {
+            (None, None)
+        } else if let Ok(json) = serde_json::from_slice(&value.payload) {
+            (Some(json), None)
+        } else {
+            (None, Some(BASE64.encode(&value.payload)))
+        };
+
+        HttpEvent {
+            index: value.index,
+            name: value.name,
+            timestamp: value.timestamp,
+            ingested_at: value.ingested_at,
+            sample_rate_per_million: value.sample_rate_per_million,
+            user: value.user,
+            source: value.source,
+            payload,
+            payload_base64,
+        }
+    }
+}
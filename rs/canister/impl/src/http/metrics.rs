@@ -0,0 +1,170 @@
+use crate::env;
+use crate::http::{is_authorized, response_from_status_code};
+use crate::state::State;
+use ic_http_certification::{HttpRequest, HttpResponse, HttpResponseBuilder};
+use std::fmt::Write;
+
+// Serves `/metrics` in the Prometheus text exposition format
+pub fn process_request(state: &State, request: &HttpRequest) -> HttpResponse<'static> {
+    if !is_authorized(state.metrics_http_api(), request) {
+        return response_from_status_code(401);
+    }
+
+    let stats = state.events().stats();
+    let mut metrics = MetricsBuilder::default();
+
+    metrics.add(
+        "event_store_events_total",
+        "counter",
+        "Total number of events stored",
+        stats.event_count,
+    );
+    metrics.add(
+        "event_store_strings_total",
+        "gauge",
+        "Number of distinct strings (names, users and sources) in the string map",
+        stats.string_count,
+    );
+    metrics.add(
+        "event_store_dedup_keys",
+        "gauge",
+        "Number of idempotency keys within the dedup window",
+        state.event_deduper_len() as u64,
+    );
+    metrics.add(
+        "event_store_stable_memory_bytes",
+        "gauge",
+        "Stable memory used by the canister",
+        env::stable_memory_bytes(),
+    );
+    metrics.add(
+        "event_store_heap_memory_bytes",
+        "gauge",
+        "Heap memory used by the canister",
+        env::heap_memory_bytes(),
+    );
+    metrics.add(
+        "event_store_cycles_balance",
+        "gauge",
+        "Cycles balance of the canister",
+        env::cycles_balance(),
+    );
+    // Alerting on the growth of the number of distinct names catches producers which accidentally
+    // include unique values (eg. ids) in event names
+    metrics.add_labelled(
+        "event_store_distinct_values",
+        "gauge",
+        "Number of distinct values seen per event field",
+        "field",
+        vec![
+            ("name".to_string(), stats.distinct_names),
+            ("user".to_string(), stats.distinct_users),
+            ("source".to_string(), stats.distinct_sources),
+        ],
+    );
+    if let Some(max_distinct_names) = state.event_limits().max_distinct_names() {
+        metrics.add(
+            "event_store_distinct_names_limit",
+            "gauge",
+            "Maximum number of distinct event names",
+            max_distinct_names,
+        );
+    }
+    metrics.add_labelled(
+        "event_store_rejected_events_total",
+        "counter",
+        "Number of events rejected per reason",
+        "reason",
+        state
+            .rejected_events_per_reason()
+            .iter()
+            .map(|(reason, count)| (reason.clone(), *count))
+            .collect(),
+    );
+    metrics.add(
+        "event_store_events_sampled_out_total",
+        "counter",
+        "Number of events dropped by sampling",
+        state.sampling().sampled_out(),
+    );
+    metrics.add_labelled(
+        "event_store_events_by_name_total",
+        "counter",
+        "Number of events stored per event name",
+        "name",
+        stats.events_per_name,
+    );
+    metrics.add_labelled(
+        "event_store_stream_events",
+        "gauge",
+        "Number of events retained per named stream",
+        "stream",
+        state
+            .streams()
+            .iter()
+            .map(|(name, stream)| (name.clone(), stream.count()))
+            .collect(),
+    );
+
+    let body = metrics.build().into_bytes();
+
+    HttpResponseBuilder::new()
+        .with_status_code(200.try_into().unwrap())
+        .with_headers(vec![
+            (
+                "content-type".to_string(),
+                "text/plain; version=0.0.4".to_string(),
+            ),
+            ("content-length".to_string(), body.len().to_string()),
+            ("cache-control".to_string(), "no-store".to_string()),
+        ])
+        .with_body(body)
+        .build()
+}
+
+#[derive(Default)]
+struct MetricsBuilder {
+    text: String,
+}
+
+impl MetricsBuilder {
+    fn add(&mut self, name: &str, metric_type: &str, help: &str, value: impl ToString) {
+        self.add_header(name, metric_type, help);
+        writeln!(self.text, "{name} {}", value.to_string()).unwrap();
+    }
+
+    fn add_labelled(
+        &mut self,
+        name: &str,
+        metric_type: &str,
+        help: &str,
+        label: &str,
+        values: Vec<(String, u64)>,
+    ) {
+        self.add_header(name, metric_type, help);
+        for (label_value, value) in values {
+            writeln!(
+                self.text,
+                "{name}{{{label}=\"{}\"}} {value}",
+                escape_label_value(&label_value)
+            )
+            .unwrap();
+        }
+    }
+
+    fn add_header(&mut self, name: &str, metric_type: &str, help: &str) {
+        writeln!(self.text, "# HELP {name} {help}").unwrap();
+        writeln!(self.text, "# TYPE {name} {metric_type}").unwrap();
+    }
+
+    fn build(self) -> String {
+        self.text
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,68 @@
+use crate::model::events_http_api::EventsHttpApi;
+use crate::state::State;
+use ic_http_certification::{HttpRequest, HttpResponse, HttpResponseBuilder};
+
+#[cfg(feature = "dapp-radar")]
+pub mod dapp_radar;
+mod events;
+mod metrics;
+
+// Builds the (uncertified) response for a request. This is used both when serving requests and
+// when certifying responses, so it must only depend on the request and the current state.
+pub fn handle_request(state: &State, request: &HttpRequest) -> HttpResponse<'static> {
+    let Ok(path) = request.get_path() else {
+        return response_from_status_code(404);
+    };
+    let segments: Vec<_> = path.split('/').skip(1).collect();
+
+    let qs = request.get_query().ok().flatten();
+
+    match segments.first() {
+        Some(&"events") if segments.len() == 1 => {
+            return events::process_request(state, request, qs.as_deref());
+        }
+        Some(&"metrics") if segments.len() == 1 => {
+            return metrics::process_request(state, request);
+        }
+        #[cfg(feature = "dapp-radar")]
+        Some(&"dapp-radar") => {
+            if let Some(response) = dapp_radar::process_request(state, &segments, qs.as_deref()) {
+                return response;
+            }
+        }
+        _ => {}
+    }
+
+    response_from_status_code(404)
+}
+
+// Requests for events or metrics must include a valid token in an `Authorization: Bearer <token>`
+// header unless they have been made public. Tokens aren't accepted as query parameters since URLs
+// tend to end up in logs. Events and metrics are each configured separately.
+fn is_authorized(access: &EventsHttpApi, request: &HttpRequest) -> bool {
+    let token = request
+        .headers()
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+        .and_then(|(_, value)| value.strip_prefix("Bearer "));
+
+    access.is_authorized(token)
+}
+
+#[cfg_attr(not(feature = "dapp-radar"), allow(dead_code))]
+pub fn json_response(body: Vec<u8>) -> HttpResponse<'static> {
+    HttpResponseBuilder::new()
+        .with_status_code(200.try_into().unwrap())
+        .with_headers(vec![
+            ("content-type".to_string(), "application/json".to_string()),
+            ("content-length".to_string(), body.len().to_string()),
+        ])
+        .with_body(body)
+        .build()
+}
+
+fn response_from_status_code<'a>(status_code: u16) -> HttpResponse<'a> {
+    HttpResponseBuilder::new()
+        .with_status_code(status_code.try_into().unwrap())
+        .build()
+}
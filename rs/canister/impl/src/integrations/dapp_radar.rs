@@ -1,19 +1,73 @@
-use event_store_types::IndexedEvent;
+use crate::memory::{
+    Memory, get_dapp_radar_daily_memory, get_dapp_radar_day_summaries_memory,
+    get_dapp_radar_hourly_memory,
+};
+use event_store_canister::DappRadarConfig;
+use event_store_types::{IndexedEvent, TimestampMillis};
+use ic_stable_structures::storable::Bound;
+use ic_stable_structures::{StableBTreeMap, Storable};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::ops::Range;
 
-const HOURLY_MAX_ENTRIES: usize = 24 * 70;
+const HOUR_IN_MS: u64 = 60 * 60 * 1000;
+const DAY_IN_MS: u64 = 24 * HOUR_IN_MS;
+const HOURLY_RETENTION_HOURS: u32 = 24 * 70;
+const DAILY_RETENTION_DAYS: u32 = 400;
+const MAX_ENTRIES_PRUNED_PER_EVENT: usize = 10;
 const PAGE_SIZE: usize = 1000;
 
-#[derive(Serialize, Deserialize, Default)]
+// Version 0 held the aggregated data on the heap, version 1 moved it into stable memory
+const DATA_VERSION: u32 = 1;
+
+// (year, month, day)
+pub type DateKey = (u32, u8, u8);
+
+// Days and hours are stored as the number of whole days or hours since the Unix epoch
+type Day = u32;
+type Hour = u32;
+
+#[derive(Serialize, Deserialize)]
 pub struct DappRadarData {
-    daily: BTreeMap<(u32, u8, u8), EventsPerUser>,
-    hourly: BTreeMap<(u32, u8, u8, u8), EventsPerUser>,
+    #[serde(skip, default = "init_daily")]
+    daily: StableBTreeMap<PeriodUser, u32, Memory>,
+    #[serde(skip, default = "init_hourly")]
+    hourly: StableBTreeMap<PeriodUser, u32, Memory>,
+    // Summaries are kept for every day, so totals remain available once the per user data for a
+    // day has been pruned
+    #[serde(skip, default = "init_day_summaries")]
+    day_summaries: StableBTreeMap<Day, DaySummary, Memory>,
+    #[serde(default)]
+    latest_hour: Hour,
     next_event_index: u64,
+    #[serde(default)]
+    config: DappRadarConfig,
+    #[serde(default)]
+    data_version: u32,
+    // The HTTP responses of each view of each day are certified once the day is over. None of
+    // these are persisted since the certifications are rebuilt after each upgrade.
+    #[serde(skip)]
+    views_pending_certification: BTreeSet<(Day, DappRadarView)>,
+    #[serde(skip)]
+    certified_views: BTreeSet<(Day, DappRadarView)>,
+    // Certified views whose data has changed, so whose certifications must be removed
+    #[serde(skip)]
+    invalidated_views: Vec<(Day, DappRadarView)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DappRadarView {
+    Daily,
+    Hourly,
+    Summary,
 }
 
 impl DappRadarData {
-    pub fn push_event(&mut self, event: &IndexedEvent) {
+    // Retention is measured back from the latest hour seen, so events timestamped later than `now`
+    // are aggregated but don't advance it, otherwise a single future-dated event could cause the
+    // data for genuine recent days to be pruned
+    pub fn push_event(&mut self, event: &IndexedEvent, now: TimestampMillis) {
         if event.index != self.next_event_index {
             return;
         }
@@ -23,80 +77,309 @@ impl DappRadarData {
             return;
         };
 
-        let datetime =
-            time::OffsetDateTime::from_unix_timestamp((event.timestamp / 1000) as i64).unwrap();
+        if !self.is_transaction(&event.name) {
+            return;
+        }
 
-        let year = datetime.year() as u32;
-        let month = datetime.month() as u8;
-        let day = datetime.day();
-        let hour = datetime.hour();
+        let hour = (event.timestamp / HOUR_IN_MS) as Hour;
+        let day = hour / 24;
+        if event.timestamp <= now {
+            self.latest_hour = self.latest_hour.max(hour);
+        }
 
-        let day_key = (year, month, day);
-        let hour_key = (year, month, day, hour);
+        let mut summary = self.day_summaries.get(&day).unwrap_or_default();
+        summary.transactions += 1;
 
-        self.daily.entry(day_key).or_default().push(user.clone());
-        self.hourly.entry(hour_key).or_default().push(user);
+        // Events for days which have already been pruned only contribute to the transactions
+        // count, since we can no longer tell whether their users are unique for that day
+        if day >= self.daily_cutoff() {
+            let key = PeriodUser(day, user.clone());
+            let count = self.daily.get(&key).unwrap_or_default();
+            if count == 0 {
+                summary.unique_wallets += 1;
+            }
+            self.daily.insert(key, count + 1);
+            self.on_view_modified(day, DappRadarView::Daily);
+        }
+        self.day_summaries.insert(day, summary);
+        self.on_view_modified(day, DappRadarView::Summary);
 
-        while self.hourly.len() > HOURLY_MAX_ENTRIES {
-            self.hourly.pop_first();
+        if hour >= self.hourly_cutoff() {
+            let key = PeriodUser(hour, user);
+            let count = self.hourly.get(&key).unwrap_or_default();
+            self.hourly.insert(key, count + 1);
+            self.on_view_modified(day, DappRadarView::Hourly);
         }
+
+        self.prune();
     }
 
     pub fn next_event_index(&self) -> u64 {
         self.next_event_index
     }
 
+    // If the config has changed, the aggregated data is cleared and `next_event_index` is reset so
+    // that the data gets rebuilt from the full set of events by the integrations backfill job
+    pub fn set_config(&mut self, config: DappRadarConfig) {
+        if config != self.config {
+            self.config = config;
+            self.reset();
+        }
+    }
+
+    // Data from older versions is discarded, then rebuilt by the integrations backfill job
+    pub fn migrate_if_required(&mut self) -> bool {
+        if self.data_version == DATA_VERSION {
+            return false;
+        }
+
+        self.reset();
+        self.data_version = DATA_VERSION;
+        true
+    }
+
+    // Queues every view of each day within the retention period for certification
+    pub fn queue_views_for_certification(&mut self) {
+        let daily_cutoff = self.daily_cutoff();
+        let hourly_cutoff = self.hourly_cutoff();
+
+        for day in self.day_summaries.keys_range(daily_cutoff..) {
+            self.views_pending_certification
+                .insert((day, DappRadarView::Daily));
+            self.views_pending_certification
+                .insert((day, DappRadarView::Summary));
+            if day * 24 >= hourly_cutoff {
+                self.views_pending_certification
+                    .insert((day, DappRadarView::Hourly));
+            }
+        }
+    }
+
+    pub fn take_invalidated_views(&mut self) -> Vec<(DateKey, DappRadarView)> {
+        std::mem::take(&mut self.invalidated_views)
+            .into_iter()
+            .map(|(day, view)| (to_date_key(day), view))
+            .collect()
+    }
+
+    // Returns the next view which is ready to be certified, which is once its day is over. Views
+    // which are no longer within the retention period are never certified.
+    pub fn next_view_to_certify(
+        &mut self,
+        now: TimestampMillis,
+    ) -> Option<(DateKey, DappRadarView)> {
+        let today = (now / DAY_IN_MS) as Day;
+        let daily_cutoff = self.daily_cutoff();
+        let hourly_cutoff = self.hourly_cutoff();
+
+        while let Some((day, view)) = self.views_pending_certification.first().copied() {
+            let retained = match view {
+                DappRadarView::Daily | DappRadarView::Summary => day >= daily_cutoff,
+                DappRadarView::Hourly => day * 24 >= hourly_cutoff,
+            };
+
+            if !retained {
+                self.views_pending_certification.pop_first();
+            } else if day < today {
+                return Some((to_date_key(day), view));
+            } else {
+                break;
+            }
+        }
+        None
+    }
+
+    pub fn mark_certified(&mut self, date: DateKey, view: DappRadarView) {
+        if let Some(day) = to_day(date) {
+            self.views_pending_certification.remove(&(day, view));
+            self.certified_views.insert((day, view));
+        }
+    }
+
     pub fn hourly(&self, year: u32, month: u8, day: u8, page: usize) -> DappRadarResponse {
-        let all_results: Vec<_> = self
-            .hourly
-            .range(&(year, month, day, 0)..&(year, month, day + 1, 0))
-            .flat_map(|((_, _, _, hour), events)| {
-                events
-                    .per_user
-                    .iter()
-                    .map(move |(user, count)| DappRadarResponseEntry {
-                        date_time: Some(format!("{year}-{month:02}-{day:02} {hour:02}:00:00")),
-                        user: user.clone(),
-                        transactions: *count,
-                    })
-            })
-            .collect();
+        let date = (year, month, day);
+        self.hourly_range(date, date, page)
+    }
+
+    pub fn hourly_range(&self, start: DateKey, end: DateKey, page: usize) -> DappRadarResponse {
+        let (Some(start), Some(end)) = (to_day(start), to_day(end)) else {
+            return DappRadarResponse::default();
+        };
+
+        let range = key_range(start * 24, (end + 1) * 24);
+        let total = self.hourly.range(range.clone()).count();
 
-        Self::extract_page(all_results, page)
+        Self::extract_page(
+            self.hourly
+                .range(range)
+                .map(|(PeriodUser(hour, user), count)| DappRadarResponseEntry {
+                    date_time: Some(format!("{} {:02}:00:00", format_day(hour / 24), hour % 24)),
+                    user,
+                    transactions: count,
+                }),
+            total,
+            page,
+        )
     }
 
     pub fn daily(&self, year: u32, month: u8, day: u8, page: usize) -> DappRadarResponse {
-        let all_results: Vec<_> = self
+        let date = (year, month, day);
+        self.daily_range_inner(date, date, false, page)
+    }
+
+    // Unlike the single day response, each entry includes its date so that the days can be told
+    // apart
+    pub fn daily_range(&self, start: DateKey, end: DateKey, page: usize) -> DappRadarResponse {
+        self.daily_range_inner(start, end, true, page)
+    }
+
+    // The per day values come from the day summaries so are available for all days, whereas the
+    // number of unique wallets across the whole range only covers days which haven't been pruned
+    pub fn summary(&self, start: DateKey, end: DateKey) -> DappRadarSummaryResponse {
+        let (Some(start), Some(end)) = (to_day(start), to_day(end)) else {
+            return DappRadarSummaryResponse::default();
+        };
+
+        let mut results = Vec::new();
+        let mut total_transactions = 0;
+
+        for (day, summary) in self.day_summaries.range(start..=end) {
+            total_transactions += summary.transactions;
+
+            results.push(DappRadarSummaryEntry {
+                date: format_day(day),
+                transactions: summary.transactions,
+                unique_wallets: summary.unique_wallets,
+            });
+        }
+
+        let unique_wallets: BTreeSet<_> = self
             .daily
-            .range(&(year, month, day)..&(year, month, day + 1))
-            .flat_map(|((_, _, _), events)| {
-                events
-                    .per_user
-                    .iter()
-                    .map(|(user, count)| DappRadarResponseEntry {
-                        date_time: None,
-                        user: user.clone(),
-                        transactions: *count,
-                    })
-            })
+            .range(key_range(start, end + 1))
+            .map(|(PeriodUser(_, user), _)| user)
             .collect();
 
-        Self::extract_page(all_results, page)
+        DappRadarSummaryResponse {
+            results,
+            total_transactions,
+            unique_wallets: unique_wallets.len() as u32,
+        }
+    }
+
+    fn daily_range_inner(
+        &self,
+        start: DateKey,
+        end: DateKey,
+        include_date: bool,
+        page: usize,
+    ) -> DappRadarResponse {
+        let (Some(start), Some(end)) = (to_day(start), to_day(end)) else {
+            return DappRadarResponse::default();
+        };
+
+        let range = key_range(start, end + 1);
+        let total = self.daily.range(range.clone()).count();
+
+        Self::extract_page(
+            self.daily
+                .range(range)
+                .map(|(PeriodUser(day, user), count)| DappRadarResponseEntry {
+                    date_time: include_date.then(|| format_day(day)),
+                    user,
+                    transactions: count,
+                }),
+            total,
+            page,
+        )
+    }
+
+    fn is_transaction(&self, event_name: &str) -> bool {
+        let config = &self.config;
+
+        (config.transaction_event_names.is_empty()
+            && config.transaction_event_name_prefixes.is_empty())
+            || config
+                .transaction_event_names
+                .iter()
+                .any(|n| n == event_name)
+            || config
+                .transaction_event_name_prefixes
+                .iter()
+                .any(|p| event_name.starts_with(p.as_str()))
+    }
+
+    fn daily_cutoff(&self) -> Day {
+        (self.latest_hour / 24).saturating_sub(DAILY_RETENTION_DAYS - 1)
+    }
+
+    fn hourly_cutoff(&self) -> Hour {
+        self.latest_hour.saturating_sub(HOURLY_RETENTION_HOURS - 1)
+    }
+
+    fn on_view_modified(&mut self, day: Day, view: DappRadarView) {
+        if self.certified_views.remove(&(day, view)) {
+            self.invalidated_views.push((day, view));
+        }
+        self.views_pending_certification.insert((day, view));
+    }
+
+    // Prunes a bounded number of entries each time so that the cost of dropping a whole day's data
+    // is spread across many events
+    fn prune(&mut self) {
+        let daily_cutoff = self.daily_cutoff();
+        for _ in 0..MAX_ENTRIES_PRUNED_PER_EVENT {
+            match self.daily.first_key_value() {
+                Some((key, _)) if key.0 < daily_cutoff => {
+                    let day = key.0;
+                    self.daily.remove(&key);
+                    // The summary includes the number of unique wallets, which is calculated
+                    // from the daily data
+                    self.on_view_modified(day, DappRadarView::Daily);
+                    self.on_view_modified(day, DappRadarView::Summary);
+                }
+                _ => break,
+            };
+        }
+
+        let hourly_cutoff = self.hourly_cutoff();
+        for _ in 0..MAX_ENTRIES_PRUNED_PER_EVENT {
+            match self.hourly.first_key_value() {
+                Some((key, _)) if key.0 < hourly_cutoff => {
+                    self.hourly.remove(&key);
+                    self.on_view_modified(key.0 / 24, DappRadarView::Hourly);
+                }
+                _ => break,
+            };
+        }
+    }
+
+    fn reset(&mut self) {
+        self.daily.clear_new();
+        self.hourly.clear_new();
+        self.day_summaries.clear_new();
+        self.latest_hour = 0;
+        self.next_event_index = 0;
+        self.views_pending_certification.clear();
+        self.invalidated_views
+            .extend(std::mem::take(&mut self.certified_views));
     }
 
-    fn extract_page(all_results: Vec<DappRadarResponseEntry>, page: usize) -> DappRadarResponse {
-        if all_results.is_empty() {
+    fn extract_page(
+        all_results: impl Iterator<Item = DappRadarResponseEntry>,
+        total: usize,
+        page: usize,
+    ) -> DappRadarResponse {
+        if total == 0 {
             return DappRadarResponse::default();
         }
 
-        let page_count = (((all_results.len() - 1) / PAGE_SIZE) + 1) as u32;
+        let page_count = (((total - 1) / PAGE_SIZE) + 1) as u32;
 
         DappRadarResponse {
             results: if page == 0 {
                 Vec::new()
             } else {
                 all_results
-                    .into_iter()
                     .skip(page.saturating_sub(1) * PAGE_SIZE)
                     .take(PAGE_SIZE)
                     .collect()
@@ -106,9 +389,107 @@ impl DappRadarData {
     }
 }
 
-#[derive(Serialize, Deserialize, Default)]
-pub struct EventsPerUser {
-    per_user: BTreeMap<String, u32>,
+impl Default for DappRadarData {
+    fn default() -> Self {
+        DappRadarData {
+            daily: init_daily(),
+            hourly: init_hourly(),
+            day_summaries: init_day_summaries(),
+            latest_hour: 0,
+            next_event_index: 0,
+            config: DappRadarConfig::default(),
+            data_version: DATA_VERSION,
+            views_pending_certification: BTreeSet::new(),
+            certified_views: BTreeSet::new(),
+            invalidated_views: Vec::new(),
+        }
+    }
+}
+
+fn init_daily() -> StableBTreeMap<PeriodUser, u32, Memory> {
+    StableBTreeMap::init(get_dapp_radar_daily_memory())
+}
+
+fn init_hourly() -> StableBTreeMap<PeriodUser, u32, Memory> {
+    StableBTreeMap::init(get_dapp_radar_hourly_memory())
+}
+
+fn init_day_summaries() -> StableBTreeMap<Day, DaySummary, Memory> {
+    StableBTreeMap::init(get_dapp_radar_day_summaries_memory())
+}
+
+// Covers all users for the days (or hours) in the range `start..end`
+fn key_range(start: u32, end: u32) -> Range<PeriodUser> {
+    PeriodUser(start, String::new())..PeriodUser(end, String::new())
+}
+
+fn to_day((year, month, day): DateKey) -> Option<Day> {
+    let month = time::Month::try_from(month).ok()?;
+    let date = time::Date::from_calendar_date(year as i32, month, day).ok()?;
+    Day::try_from(date.midnight().assume_utc().unix_timestamp() / (24 * 60 * 60)).ok()
+}
+
+fn to_date_key(day: Day) -> DateKey {
+    let date = time::OffsetDateTime::from_unix_timestamp(day as i64 * 24 * 60 * 60)
+        .unwrap()
+        .date();
+
+    (date.year() as u32, date.month() as u8, date.day())
+}
+
+fn format_day(day: Day) -> String {
+    let (year, month, day) = to_date_key(day);
+    format!("{year}-{month:02}-{day:02}")
+}
+
+// A day or hour followed by a user. Tuples containing unbounded types such as `String` can't be
+// stored in a `StableBTreeMap`, so the daily and hourly maps are keyed by this instead.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct PeriodUser(u32, String);
+
+impl Storable for PeriodUser {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(4 + self.1.len());
+        bytes.extend_from_slice(&self.0.to_be_bytes());
+        bytes.extend_from_slice(self.1.as_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        PeriodUser(
+            u32::from_be_bytes(bytes[..4].try_into().unwrap()),
+            String::from_utf8(bytes[4..].to_vec()).unwrap(),
+        )
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(Default)]
+struct DaySummary {
+    transactions: u64,
+    unique_wallets: u32,
+}
+
+impl Storable for DaySummary {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(12);
+        bytes.extend_from_slice(&self.transactions.to_be_bytes());
+        bytes.extend_from_slice(&self.unique_wallets.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        DaySummary {
+            transactions: u64::from_be_bytes(bytes[..8].try_into().unwrap()),
+            unique_wallets: u32::from_be_bytes(bytes[8..12].try_into().unwrap()),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 12,
+        is_fixed_size: true,
+    };
 }
 
 #[derive(Serialize, Default)]
@@ -118,6 +499,12 @@ pub struct DappRadarResponse {
     page_count: u32,
 }
 
+impl DappRadarResponse {
+    pub fn page_count(&self) -> u32 {
+        self.page_count
+    }
+}
+
 #[derive(Serialize)]
 struct DappRadarResponseEntry {
     #[serde(rename = "dateTime", skip_serializing_if = "Option::is_none")]
@@ -126,8 +513,83 @@ struct DappRadarResponseEntry {
     transactions: u32,
 }
 
-impl EventsPerUser {
-    fn push(&mut self, user: String) {
-        *self.per_user.entry(user).or_default() += 1;
+#[derive(Serialize, Default)]
+pub struct DappRadarSummaryResponse {
+    results: Vec<DappRadarSummaryEntry>,
+    #[serde(rename = "totalTransactions")]
+    total_transactions: u64,
+    #[serde(rename = "uniqueWallets")]
+    unique_wallets: u32,
+}
+
+#[derive(Serialize)]
+struct DappRadarSummaryEntry {
+    date: String,
+    transactions: u64,
+    #[serde(rename = "uniqueWallets")]
+    unique_wallets: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: TimestampMillis = 1_746_000_000_000;
+
+    #[test]
+    fn future_dated_event_does_not_prune_recent_data() {
+        let mut data = DappRadarData::default();
+        let today = (NOW / DAY_IN_MS) as Day;
+
+        data.push_event(&event(0, NOW - HOUR_IN_MS, "alice"), NOW);
+        data.push_event(&event(1, NOW + 1000 * DAY_IN_MS, "bob"), NOW);
+        data.push_event(&event(2, NOW, "carol"), NOW);
+
+        assert_eq!(data.latest_hour, (NOW / HOUR_IN_MS) as Hour);
+        assert_eq!(data.daily.range(key_range(today, today + 1)).count(), 2);
+        assert_eq!(
+            data.hourly
+                .range(key_range(today * 24, (today + 1) * 24))
+                .count(),
+            2
+        );
+        assert_eq!(data.day_summaries.get(&today).unwrap().transactions, 2);
+    }
+
+    #[test]
+    fn migrate_if_required_resets_data_from_older_versions() {
+        let mut data = DappRadarData::default();
+        let today = (NOW / DAY_IN_MS) as Day;
+
+        data.push_event(&event(0, NOW, "alice"), NOW);
+        assert!(!data.migrate_if_required());
+        assert_eq!(data.next_event_index(), 1);
+
+        data.data_version = 0;
+        assert!(data.migrate_if_required());
+        assert_eq!(data.data_version, DATA_VERSION);
+        assert_eq!(data.next_event_index(), 0);
+        assert_eq!(data.latest_hour, 0);
+        assert!(data.daily.is_empty());
+        assert!(data.hourly.is_empty());
+        assert!(data.day_summaries.is_empty());
+
+        // The data is then rebuilt from the start by the integrations backfill job
+        data.push_event(&event(0, NOW, "alice"), NOW);
+        assert_eq!(data.day_summaries.get(&today).unwrap().transactions, 1);
+        assert!(!data.migrate_if_required());
+    }
+
+    fn event(index: u64, timestamp: TimestampMillis, user: &str) -> IndexedEvent {
+        IndexedEvent {
+            index,
+            name: "swap".to_string(),
+            timestamp,
+            ingested_at: Some(NOW),
+            sample_rate_per_million: None,
+            user: Some(user.to_string()),
+            source: None,
+            payload: Vec::new(),
+        }
     }
 }
@@ -0,0 +1,34 @@
+use crate::{env, state};
+use std::cell::Cell;
+use std::time::Duration;
+
+const MAX_INSTRUCTIONS_PER_BATCH: u64 = 5_000_000_000;
+const EVENTS_PER_CHUNK: u64 = 100;
+
+thread_local! {
+    static JOB_SCHEDULED: Cell<bool> = Cell::default();
+}
+
+pub fn start_job_if_required() -> bool {
+    if !JOB_SCHEDULED.get() && state::read(|s| s.field_values_backfill_required()) {
+        JOB_SCHEDULED.set(true);
+        ic_cdk_timers::set_timer(Duration::ZERO, run);
+        true
+    } else {
+        false
+    }
+}
+
+fn run() {
+    JOB_SCHEDULED.set(false);
+
+    state::mutate(|s| {
+        while env::instruction_counter() < MAX_INSTRUCTIONS_PER_BATCH
+            && s.field_values_backfill_required()
+        {
+            s.backfill_field_values(EVENTS_PER_CHUNK);
+        }
+    });
+
+    start_job_if_required();
+}
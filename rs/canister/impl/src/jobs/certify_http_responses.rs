@@ -0,0 +1,37 @@
+use crate::{env, state};
+use std::cell::Cell;
+use std::time::Duration;
+
+const MAX_INSTRUCTIONS_PER_BATCH: u64 = 5_000_000_000;
+
+thread_local! {
+    static JOB_SCHEDULED: Cell<bool> = Cell::default();
+}
+
+// Responses become certifiable once the day they cover is over, so this is also run periodically
+pub fn start_job_if_required() -> bool {
+    let now = env::time();
+    if !JOB_SCHEDULED.get() && state::mutate(|s| s.has_http_routes_to_certify(now)) {
+        JOB_SCHEDULED.set(true);
+        ic_cdk_timers::set_timer(Duration::ZERO, run);
+        true
+    } else {
+        false
+    }
+}
+
+fn run() {
+    JOB_SCHEDULED.set(false);
+
+    let now = env::time();
+    state::mutate(|s| {
+        while env::instruction_counter() < MAX_INSTRUCTIONS_PER_BATCH {
+            if !s.certify_next_http_route(now) {
+                break;
+            }
+        }
+        s.update_certified_data();
+    });
+
+    start_job_if_required();
+}
@@ -0,0 +1,4 @@
+pub mod backfill_field_values;
+pub mod certify_http_responses;
+pub mod populate_integrations_data;
+pub mod prune_streams;
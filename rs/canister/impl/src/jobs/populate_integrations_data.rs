@@ -0,0 +1,45 @@
+use crate::{env, state};
+use std::cell::Cell;
+use std::time::Duration;
+
+// Leaves plenty of headroom below the 40B instruction limit which applies to timer executions
+const MAX_INSTRUCTIONS_PER_BATCH: u64 = 5_000_000_000;
+const EVENTS_PER_CHUNK: u64 = 100;
+
+thread_local! {
+    static JOB_SCHEDULED: Cell<bool> = Cell::default();
+}
+
+pub fn start_job_if_required() -> bool {
+    if !JOB_SCHEDULED.get() && state::read(|s| s.integrations_backfill_required()) {
+        JOB_SCHEDULED.set(true);
+        ic_cdk_timers::set_timer(Duration::ZERO, run);
+        true
+    } else {
+        false
+    }
+}
+
+fn run() {
+    JOB_SCHEDULED.set(false);
+
+    let now = env::time();
+
+    state::mutate(|s| {
+        while env::instruction_counter() < MAX_INSTRUCTIONS_PER_BATCH {
+            let events_count = s.events().count();
+            let Some((integration, next)) = s.integrations_data().next_to_backfill(events_count)
+            else {
+                break;
+            };
+
+            for event in s.events().get(next, EVENTS_PER_CHUNK) {
+                s.integrations_data_mut()
+                    .push_event_to(integration, &event, now);
+            }
+        }
+        s.invalidate_certified_responses();
+    });
+
+    start_job_if_required();
+}
@@ -1,6 +1,8 @@
 mod env;
 mod guards;
+mod http;
 mod integrations;
+mod jobs;
 mod lifecycle;
 mod memory;
 mod model;
@@ -1,3 +1,4 @@
+use crate::lifecycle;
 use crate::state;
 use crate::state::State;
 use event_store_canister::InitArgs;
@@ -6,11 +7,36 @@ use std::time::Duration;
 
 #[init]
 fn init(args: InitArgs) {
-    state::init(State::new(
+    let mut state = State::new(
         args.push_events_whitelist.into_iter().collect(),
         args.read_events_whitelist.into_iter().collect(),
         args.time_granularity,
-    ));
+    );
+    state
+        .integrations_data_mut()
+        .set_config(args.dapp_radar_config);
+    if let Some(config) = args.events_http_api_config {
+        state.events_http_api_mut().set_config(config);
+    }
+    if let Some(config) = args.metrics_http_api_config {
+        state.metrics_http_api_mut().set_config(config);
+    }
+    if let Some(config) = args.producer_limits {
+        state.producers_mut().set_limits(config);
+    }
+    if let Some(config) = args.event_limits {
+        state.event_limits_mut().set_config(config);
+    }
+    if let Some(timestamp_policy) = args.timestamp_policy {
+        state.set_timestamp_policy(timestamp_policy);
+    }
+
+    state.init_certified_responses();
+
+    state::init(state);
+
+    lifecycle::start_certify_http_responses_timer();
+    lifecycle::start_prune_streams_timer();
 
     ic_cdk_timers::set_timer(Duration::ZERO, || {
         ic_cdk::futures::spawn(async {
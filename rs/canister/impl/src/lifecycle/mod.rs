@@ -1,5 +1,25 @@
+use crate::jobs;
+use std::time::Duration;
+
 mod init;
 mod post_upgrade;
 mod pre_upgrade;
 
 const READER_WRITER_BUFFER_SIZE: usize = 1024 * 1024; // 1MB
+
+const CERTIFY_HTTP_RESPONSES_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const PRUNE_STREAMS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Responses covering a given day only become certifiable once that day is over
+fn start_certify_http_responses_timer() {
+    ic_cdk_timers::set_timer_interval(CERTIFY_HTTP_RESPONSES_INTERVAL, || {
+        jobs::certify_http_responses::start_job_if_required();
+    });
+}
+
+// Streams are also pruned as events are pushed, this catches streams which are no longer pushed to
+fn start_prune_streams_timer() {
+    ic_cdk_timers::set_timer_interval(PRUNE_STREAMS_INTERVAL, || {
+        jobs::prune_streams::start_job_if_required();
+    });
+}
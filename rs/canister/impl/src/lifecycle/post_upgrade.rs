@@ -1,41 +1,52 @@
+use crate::jobs;
+use crate::lifecycle;
 use crate::lifecycle::READER_WRITER_BUFFER_SIZE;
 use crate::memory::get_upgrades_memory;
 use crate::state;
 use crate::state::State;
+use event_store_canister::UpgradeArgs;
 use ic_cdk::post_upgrade;
 use ic_stable_structures::reader::{BufferedReader, Reader};
 use serde::Deserialize;
-use std::time::Duration;
 
 #[post_upgrade]
-fn post_upgrade() {
+fn post_upgrade(args: Option<UpgradeArgs>) {
     let memory = get_upgrades_memory();
     let reader = BufferedReader::new(READER_WRITER_BUFFER_SIZE, Reader::new(&memory, 0));
     let mut deserializer = rmp_serde::Deserializer::new(reader);
 
-    state::init(State::deserialize(&mut deserializer).unwrap());
+    let mut state = State::deserialize(&mut deserializer).unwrap();
+    state.integrations_data_mut().migrate_if_required();
 
-    run_job_to_populate_integrations_data_if_required()
-}
-
-fn run_job_to_populate_integrations_data_if_required() {
-    state::read(|s| {
-        if let Some(next) = s.integrations_data().next_event_index() {
-            if s.events().stats().latest_event_index > Some(next) {
-                ic_cdk_timers::set_timer(Duration::ZERO, populate_integrations_data);
-            }
+    if let Some(args) = args {
+        state
+            .integrations_data_mut()
+            .set_config(args.dapp_radar_config);
+        if let Some(config) = args.events_http_api_config {
+            state.events_http_api_mut().set_config(config);
         }
-    });
-}
-
-fn populate_integrations_data() {
-    state::mutate(|s| {
-        if let Some(next) = s.integrations_data().next_event_index() {
-            let events = s.events().get(next, 10_000);
-            for event in events {
-                s.integrations_data_mut().push_event(event);
-            }
+        if let Some(config) = args.metrics_http_api_config {
+            state.metrics_http_api_mut().set_config(config);
+        }
+        if let Some(config) = args.producer_limits {
+            state.producers_mut().set_limits(config);
         }
-    });
-    run_job_to_populate_integrations_data_if_required();
+        if let Some(config) = args.event_limits {
+            state.event_limits_mut().set_config(config);
+        }
+        if let Some(timestamp_policy) = args.timestamp_policy {
+            state.set_timestamp_policy(timestamp_policy);
+        }
+    }
+
+    state.init_certified_responses();
+
+    state::init(state);
+
+    jobs::backfill_field_values::start_job_if_required();
+    jobs::populate_integrations_data::start_job_if_required();
+    jobs::certify_http_responses::start_job_if_required();
+    jobs::prune_streams::start_job_if_required();
+    lifecycle::start_certify_http_responses_timer();
+    lifecycle::start_prune_streams_timer();
 }
@@ -1,14 +1,48 @@
 use ic_stable_structures::{
-    DefaultMemoryImpl,
+    DefaultMemoryImpl, Memory as _,
     memory_manager::{MemoryId, MemoryManager, VirtualMemory},
 };
 
-const UPGRADES: MemoryId = MemoryId::new(0);
-const EVENTS_INDEX: MemoryId = MemoryId::new(1);
-const EVENTS_DATA: MemoryId = MemoryId::new(2);
-const STRING_TO_NUM_MAP: MemoryId = MemoryId::new(8);
-const NUM_TO_STRING_INDEX: MemoryId = MemoryId::new(9);
-const NUM_TO_STRING_DATA: MemoryId = MemoryId::new(10);
+const UPGRADES: u8 = 0;
+const EVENTS_INDEX: u8 = 1;
+const EVENTS_DATA: u8 = 2;
+#[cfg(feature = "dapp-radar")]
+const DAPP_RADAR_DAILY: u8 = 3;
+#[cfg(feature = "dapp-radar")]
+const DAPP_RADAR_HOURLY: u8 = 4;
+#[cfg(feature = "dapp-radar")]
+const DAPP_RADAR_DAY_SUMMARIES: u8 = 5;
+const EVENTS_PER_NAME: u8 = 6;
+const FIELD_VALUES: u8 = 7;
+const STRING_TO_NUM_MAP: u8 = 8;
+const NUM_TO_STRING_INDEX: u8 = 9;
+const NUM_TO_STRING_DATA: u8 = 10;
+const FIELD_VALUE_COUNTS: u8 = 11;
+
+// Each stream is allocated its own memory from this range when it is created
+pub const STREAM_MEMORIES_START: u8 = 64;
+// 254 is the highest memory id supported by the `MemoryManager`
+pub const STREAM_MEMORIES_END: u8 = 254;
+
+const MEMORIES: &[(u8, &str)] = &[
+    (UPGRADES, "upgrades"),
+    (EVENTS_INDEX, "events_index"),
+    (EVENTS_DATA, "events_data"),
+    #[cfg(feature = "dapp-radar")]
+    (DAPP_RADAR_DAILY, "dapp_radar_daily"),
+    #[cfg(feature = "dapp-radar")]
+    (DAPP_RADAR_HOURLY, "dapp_radar_hourly"),
+    #[cfg(feature = "dapp-radar")]
+    (DAPP_RADAR_DAY_SUMMARIES, "dapp_radar_day_summaries"),
+    (EVENTS_PER_NAME, "events_per_name"),
+    (FIELD_VALUES, "field_values"),
+    (STRING_TO_NUM_MAP, "string_to_num_map"),
+    (NUM_TO_STRING_INDEX, "num_to_string_index"),
+    (NUM_TO_STRING_DATA, "num_to_string_data"),
+    (FIELD_VALUE_COUNTS, "field_value_counts"),
+];
+
+const WASM_PAGE_SIZE_BYTES: u64 = 64 * 1024;
 
 pub type Memory = VirtualMemory<DefaultMemoryImpl>;
 
@@ -29,6 +63,33 @@ pub fn get_events_data_memory() -> Memory {
     get_memory(EVENTS_DATA)
 }
 
+#[cfg(feature = "dapp-radar")]
+pub fn get_dapp_radar_daily_memory() -> Memory {
+    get_memory(DAPP_RADAR_DAILY)
+}
+
+#[cfg(feature = "dapp-radar")]
+pub fn get_dapp_radar_hourly_memory() -> Memory {
+    get_memory(DAPP_RADAR_HOURLY)
+}
+
+#[cfg(feature = "dapp-radar")]
+pub fn get_dapp_radar_day_summaries_memory() -> Memory {
+    get_memory(DAPP_RADAR_DAY_SUMMARIES)
+}
+
+pub fn get_events_per_name_memory() -> Memory {
+    get_memory(EVENTS_PER_NAME)
+}
+
+pub fn get_field_values_memory() -> Memory {
+    get_memory(FIELD_VALUES)
+}
+
+pub fn get_field_value_counts_memory() -> Memory {
+    get_memory(FIELD_VALUE_COUNTS)
+}
+
 pub fn get_string_to_num_map_memory() -> Memory {
     get_memory(STRING_TO_NUM_MAP)
 }
@@ -41,6 +102,23 @@ pub fn get_num_to_string_data_memory() -> Memory {
     get_memory(NUM_TO_STRING_DATA)
 }
 
-fn get_memory(id: MemoryId) -> Memory {
-    MEMORY_MANAGER.with(|m| m.get(id))
+pub fn get_stream_memory(id: u8) -> Memory {
+    assert!((STREAM_MEMORIES_START..=STREAM_MEMORIES_END).contains(&id));
+    get_memory(id)
+}
+
+// Returns the id, name and size in bytes of each memory
+pub fn memory_sizes() -> Vec<(u8, &'static str, u64)> {
+    MEMORIES
+        .iter()
+        .map(|(id, name)| (*id, *name, memory_size(*id)))
+        .collect()
+}
+
+pub fn memory_size(id: u8) -> u64 {
+    get_memory(id).size() * WASM_PAGE_SIZE_BYTES
+}
+
+fn get_memory(id: u8) -> Memory {
+    MEMORY_MANAGER.with(|m| m.get(MemoryId::new(id)))
 }
@@ -0,0 +1,184 @@
+// Only the DappRadar routes are currently certifiable
+#![cfg_attr(not(feature = "dapp-radar"), allow(dead_code))]
+
+use ic_http_certification::utils::add_v2_certificate_header;
+use ic_http_certification::{
+    CERTIFICATE_EXPRESSION_HEADER_NAME, DefaultCelBuilder, DefaultResponseCertification,
+    HttpCertification, HttpCertificationPath, HttpCertificationTree, HttpCertificationTreeEntry,
+    HttpRequest, HttpResponse,
+};
+use std::collections::BTreeMap;
+
+const CERTIFIED_RESPONSE_HEADERS: &[&str] = &["content-type"];
+
+// Holds the certifications of those responses which can be certified. Any request whose path has
+// no certified response falls back to the skip certification entry, which covers all paths.
+// Neither the tree nor the certifications are persisted across upgrades, they are rebuilt instead.
+pub struct CertifiedResponses {
+    tree: HttpCertificationTree,
+    certified: BTreeMap<String, CertifiedPath>,
+}
+
+pub struct CertifiableRoute {
+    pub path: String,
+    pub certification_type: CertificationType,
+    pub requests: Vec<HttpRequest<'static>>,
+}
+
+#[derive(Clone, Copy)]
+pub enum CertificationType {
+    // Certifies the request method, body and the given query parameters, along with the response
+    Full {
+        query_parameters: &'static [&'static str],
+    },
+    ResponseOnly,
+}
+
+struct CertifiedPath {
+    certification_type: CertificationType,
+    certifications: Vec<HttpCertification>,
+}
+
+impl CertifiedResponses {
+    // Replaces any existing certifications for the route's path. The `responses` must be in the
+    // same order as the route's requests.
+    pub fn certify(&mut self, route: CertifiableRoute, responses: Vec<HttpResponse<'static>>) {
+        self.remove(&route.path);
+
+        let tree_path = HttpCertificationPath::exact(route.path.clone());
+        let certifications: Vec<_> = route
+            .requests
+            .iter()
+            .zip(responses)
+            .map(|(request, response)| certification(route.certification_type, request, response).0)
+            .collect();
+
+        for certification in certifications.iter() {
+            self.tree
+                .insert(&HttpCertificationTreeEntry::new(&tree_path, certification));
+        }
+
+        self.certified.insert(
+            route.path,
+            CertifiedPath {
+                certification_type: route.certification_type,
+                certifications,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, path: &str) {
+        if self.certified.remove(path).is_some() {
+            self.tree
+                .delete_by_path(&HttpCertificationPath::exact(path.to_string()));
+        }
+    }
+
+    // Must be called at the end of each update which modifies the tree
+    pub fn update_certified_data(&self) {
+        ic_cdk::api::certified_data_set(self.tree.root_hash());
+    }
+
+    pub fn add_certificate_headers(
+        &self,
+        request: &HttpRequest,
+        response: HttpResponse<'static>,
+    ) -> HttpResponse<'static> {
+        let Some(data_certificate) = ic_cdk::api::data_certificate() else {
+            return response;
+        };
+
+        let path = request.get_path().unwrap_or_default();
+
+        if let Some(certified_path) = self.certified.get(&path) {
+            let (certification, mut response) =
+                certification(certified_path.certification_type, request, response.clone());
+
+            if certified_path.certifications.contains(&certification) {
+                let tree_path = HttpCertificationPath::exact(path.clone());
+                let entry = HttpCertificationTreeEntry::new(&tree_path, certification);
+                let witness = self.tree.witness(&entry, &path).unwrap();
+                add_v2_certificate_header(
+                    &data_certificate,
+                    &mut response,
+                    &witness,
+                    &tree_path.to_expr_path(),
+                );
+                return response;
+            }
+        }
+
+        // If the path has certified responses but none of them match, then the response falls
+        // through to the skip certification below, which HTTP gateways will reject
+        let mut response = response;
+        response.add_header((
+            CERTIFICATE_EXPRESSION_HEADER_NAME.to_string(),
+            DefaultCelBuilder::skip_certification().to_string(),
+        ));
+        let tree_path = skip_certification_path();
+        let entry = HttpCertificationTreeEntry::new(&tree_path, HttpCertification::skip());
+        let witness = self.tree.witness(&entry, &path).unwrap();
+        add_v2_certificate_header(
+            &data_certificate,
+            &mut response,
+            &witness,
+            &tree_path.to_expr_path(),
+        );
+        response
+    }
+}
+
+impl Default for CertifiedResponses {
+    fn default() -> Self {
+        let mut tree = HttpCertificationTree::default();
+        tree.insert(&HttpCertificationTreeEntry::new(
+            skip_certification_path(),
+            HttpCertification::skip(),
+        ));
+
+        CertifiedResponses {
+            tree,
+            certified: BTreeMap::new(),
+        }
+    }
+}
+
+fn skip_certification_path() -> HttpCertificationPath<'static> {
+    HttpCertificationPath::wildcard("")
+}
+
+// Adds the certificate expression header to the response, then calculates its certification
+fn certification(
+    certification_type: CertificationType,
+    request: &HttpRequest,
+    mut response: HttpResponse<'static>,
+) -> (HttpCertification, HttpResponse<'static>) {
+    let response_certification =
+        DefaultResponseCertification::certified_response_headers(CERTIFIED_RESPONSE_HEADERS);
+
+    let certification = match certification_type {
+        CertificationType::Full { query_parameters } => {
+            let cel_expr = DefaultCelBuilder::full_certification()
+                .with_request_query_parameters(query_parameters)
+                .with_response_certification(response_certification)
+                .build();
+            response.add_header((
+                CERTIFICATE_EXPRESSION_HEADER_NAME.to_string(),
+                cel_expr.to_string(),
+            ));
+            HttpCertification::full(&cel_expr, request, &response, None)
+        }
+        CertificationType::ResponseOnly => {
+            let cel_expr = DefaultCelBuilder::response_only_certification()
+                .with_response_certification(response_certification)
+                .build();
+            response.add_header((
+                CERTIFICATE_EXPRESSION_HEADER_NAME.to_string(),
+                cel_expr.to_string(),
+            ));
+            HttpCertification::response_only(&cel_expr, &response, None)
+        }
+    };
+
+    (certification.unwrap(), response)
+}
@@ -0,0 +1,89 @@
+use crate::model::events::{EventField, Events};
+use event_store_canister::{CardinalityLimit, EventLimitsConfig, EventViolation, SizeLimit};
+use event_store_types::IdempotentEvent;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct EventLimits {
+    config: EventLimitsConfig,
+}
+
+impl EventLimits {
+    pub fn set_config(&mut self, config: EventLimitsConfig) {
+        self.config = config;
+    }
+
+    pub fn max_distinct_names(&self) -> Option<u32> {
+        self.config.max_distinct_names
+    }
+
+    // The distinct names limit only applies to the default stream, whose `events` are passed in,
+    // since named streams don't track distinct names. It is only enforced once
+    // `field_values_backfilled` is true, since until then the set of known names is incomplete, so
+    // events with existing names could be rejected.
+    pub fn validate(
+        &self,
+        event: &IdempotentEvent,
+        events: Option<&Events>,
+        field_values_backfilled: bool,
+    ) -> Vec<EventViolation> {
+        let fields = [
+            (
+                event.payload.len(),
+                self.config.max_payload_bytes,
+                EventViolation::PayloadTooLarge as fn(SizeLimit) -> EventViolation,
+            ),
+            (
+                event.name.len(),
+                self.config.max_name_bytes,
+                EventViolation::NameTooLong,
+            ),
+            (
+                event.user.as_ref().map_or(0, |u| u.as_str().len()),
+                self.config.max_user_bytes,
+                EventViolation::UserTooLong,
+            ),
+            (
+                event.source.as_ref().map_or(0, |s| s.as_str().len()),
+                self.config.max_source_bytes,
+                EventViolation::SourceTooLong,
+            ),
+        ];
+
+        let mut violations: Vec<_> = fields
+            .into_iter()
+            .filter_map(|(size, max, violation)| {
+                let max = max?;
+                let size = u32::try_from(size).unwrap_or(u32::MAX);
+                (size > max).then(|| violation(SizeLimit { size, max }))
+            })
+            .collect();
+
+        if let (Some(max), Some(events)) = (
+            self.config
+                .max_distinct_names
+                .filter(|_| field_values_backfilled),
+            events,
+        ) {
+            if events.distinct_values(EventField::Name) >= max as u64
+                && !events.is_known_value(EventField::Name, &event.name)
+            {
+                violations.push(EventViolation::TooManyDistinctNames(CardinalityLimit {
+                    max,
+                }));
+            }
+        }
+
+        violations
+    }
+}
+
+pub fn violation_reason(violation: &EventViolation) -> &'static str {
+    match violation {
+        EventViolation::PayloadTooLarge(_) => "payload_too_large",
+        EventViolation::NameTooLong(_) => "name_too_long",
+        EventViolation::UserTooLong(_) => "user_too_long",
+        EventViolation::SourceTooLong(_) => "source_too_long",
+        EventViolation::TooManyDistinctNames(_) => "too_many_distinct_names",
+    }
+}
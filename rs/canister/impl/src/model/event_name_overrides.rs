@@ -0,0 +1,59 @@
+use event_store_canister::EventNameMatcher;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+// Values which apply to events by exact name or by name prefix
+#[derive(Serialize, Deserialize)]
+pub struct EventNameOverrides<T> {
+    exact: BTreeMap<String, T>,
+    prefixes: BTreeMap<String, T>,
+}
+
+impl<T> EventNameOverrides<T> {
+    pub fn set(&mut self, matcher: EventNameMatcher, value: T) {
+        match matcher {
+            EventNameMatcher::Exact(name) => self.exact.insert(name, value),
+            EventNameMatcher::Prefix(prefix) => self.prefixes.insert(prefix, value),
+        };
+    }
+
+    pub fn remove(&mut self, matcher: &EventNameMatcher) -> bool {
+        match matcher {
+            EventNameMatcher::Exact(name) => self.exact.remove(name).is_some(),
+            EventNameMatcher::Prefix(prefix) => self.prefixes.remove(prefix).is_some(),
+        }
+    }
+
+    // An exact match takes precedence, followed by the longest matching prefix. Every prefix of
+    // `name` sorts before it, so the first match iterating backwards from `name` is the longest.
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.exact.get(name).or_else(|| {
+            self.prefixes
+                .range::<str, _>((Bound::Unbounded, Bound::Included(name)))
+                .rev()
+                .find(|(prefix, _)| name.starts_with(prefix.as_str()))
+                .map(|(_, value)| value)
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EventNameMatcher, &T)> {
+        self.exact
+            .iter()
+            .map(|(name, value)| (EventNameMatcher::Exact(name.clone()), value))
+            .chain(
+                self.prefixes
+                    .iter()
+                    .map(|(prefix, value)| (EventNameMatcher::Prefix(prefix.clone()), value)),
+            )
+    }
+}
+
+impl<T> Default for EventNameOverrides<T> {
+    fn default() -> Self {
+        EventNameOverrides {
+            exact: BTreeMap::new(),
+            prefixes: BTreeMap::new(),
+        }
+    }
+}
@@ -1,9 +1,12 @@
-use crate::memory::{Memory, get_events_data_memory, get_events_index_memory};
+use crate::memory::{
+    Memory, get_events_data_memory, get_events_index_memory, get_events_per_name_memory,
+    get_field_value_counts_memory, get_field_values_memory,
+};
 use crate::model::string_to_num_map::StringToNumMap;
 use candid::Deserialize;
 use event_store_types::{Anonymizable, IdempotentEvent, IndexedEvent, TimestampMillis};
 use ic_stable_structures::storable::Bound;
-use ic_stable_structures::{StableLog, Storable};
+use ic_stable_structures::{StableBTreeMap, StableLog, Storable};
 use serde::Serialize;
 use sha2::Digest;
 use std::borrow::Cow;
@@ -12,6 +15,21 @@ use std::fmt::Write;
 pub struct Events {
     events: StableLog<StorableEvent, Memory, Memory>,
     string_to_num_map: StringToNumMap,
+    // Keyed by the name's entry in the `string_to_num_map`. Events pushed before this map was
+    // introduced are counted by the field values backfill.
+    events_per_name: StableBTreeMap<u32, u64, Memory>,
+    // The set of distinct values seen for each field, keyed by field then by the value's entry in
+    // the `string_to_num_map`, along with the number of distinct values per field
+    field_values: StableBTreeMap<(u8, u32), (), Memory>,
+    field_value_counts: StableBTreeMap<u8, u64, Memory>,
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum EventField {
+    Name = 0,
+    User = 1,
+    Source = 2,
 }
 
 impl Events {
@@ -24,27 +42,96 @@ impl Events {
             .collect()
     }
 
-    pub fn push(&mut self, event: IdempotentEvent, salt: [u8; 32]) -> IndexedEvent {
-        let indexed = self.convert_to_indexed(event, salt);
+    // The event's field values are only recorded if `record_field_values` is true, otherwise they
+    // are left to be recorded by the backfill, so that each event is only counted once
+    pub fn push(
+        &mut self,
+        event: IdempotentEvent,
+        salt: [u8; 32],
+        now: TimestampMillis,
+        sample_rate_per_million: Option<u32>,
+        record_field_values: bool,
+    ) -> IndexedEvent {
+        let indexed =
+            to_indexed_event(event, self.events.len(), salt, now, sample_rate_per_million);
         let storable = self.convert_to_storable(&indexed);
         self.events.append(&storable).unwrap();
+        if record_field_values {
+            self.record_field_values(&storable);
+        }
         indexed
     }
 
+    // Records the field values and name counts of events which were pushed before these were
+    // tracked. Returns the index of the next event to process.
+    pub fn backfill_field_values(&mut self, start: u64, count: u64) -> u64 {
+        let end = start.saturating_add(count).min(self.events.len());
+        for index in start..end {
+            if let Some(event) = self.events.get(index) {
+                self.record_field_values(&event);
+            }
+        }
+        end
+    }
+
+    pub fn is_known_value(&self, field: EventField, value: &str) -> bool {
+        self.string_to_num_map
+            .get_num(value)
+            .is_some_and(|num| self.field_values.contains_key(&(field as u8, num)))
+    }
+
+    pub fn distinct_values(&self, field: EventField) -> u64 {
+        self.field_value_counts
+            .get(&(field as u8))
+            .unwrap_or_default()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.events.len()
+    }
+
+    pub fn latest_event_index(&self) -> Option<u64> {
+        self.events.len().checked_sub(1)
+    }
+
     pub fn stats(&self) -> EventsStats {
         EventsStats {
-            latest_event_index: self.events.len().checked_sub(1),
+            event_count: self.events.len(),
+            string_count: self.string_to_num_map.len(),
+            distinct_names: self.distinct_values(EventField::Name),
+            distinct_users: self.distinct_values(EventField::User),
+            distinct_sources: self.distinct_values(EventField::Source),
+            events_per_name: self
+                .events_per_name
+                .iter()
+                .map(|(name, count)| {
+                    let name = self
+                        .string_to_num_map
+                        .convert_to_string(name)
+                        .unwrap_or("unknown".to_string());
+                    (name, count)
+                })
+                .collect(),
         }
     }
 
-    fn convert_to_indexed(&mut self, event: IdempotentEvent, salt: [u8; 32]) -> IndexedEvent {
-        IndexedEvent {
-            index: self.events.len(),
-            name: event.name,
-            timestamp: event.timestamp,
-            user: event.user.map(|u| to_maybe_anonymized_string(u, salt)),
-            source: event.source.map(|s| to_maybe_anonymized_string(s, salt)),
-            payload: event.payload,
+    fn record_field_values(&mut self, event: &StorableEvent) {
+        let name_count = self.events_per_name.get(&event.name).unwrap_or_default();
+        self.events_per_name.insert(event.name, name_count + 1);
+
+        let fields = [
+            (EventField::Name, Some(event.name)),
+            (EventField::User, event.user),
+            (EventField::Source, event.source),
+        ];
+
+        for (field, value) in fields {
+            if let Some(value) = value {
+                if self.field_values.insert((field as u8, value), ()).is_none() {
+                    let count = self.distinct_values(field);
+                    self.field_value_counts.insert(field as u8, count + 1);
+                }
+            }
         }
     }
 
@@ -53,6 +140,8 @@ impl Events {
             index: event.index,
             name: self.string_to_num_map.convert_to_num(&event.name),
             timestamp: event.timestamp,
+            ingested_at: event.ingested_at,
+            sample_rate_per_million: event.sample_rate_per_million,
             user: event
                 .user
                 .as_ref()
@@ -73,6 +162,8 @@ impl Events {
                 .convert_to_string(event.name)
                 .unwrap_or("unknown".to_string()),
             timestamp: event.timestamp,
+            ingested_at: event.ingested_at,
+            sample_rate_per_million: event.sample_rate_per_million,
             user: event
                 .user
                 .and_then(|u| self.string_to_num_map.convert_to_string(u)),
@@ -89,6 +180,9 @@ impl Default for Events {
         Events {
             events: init_events(),
             string_to_num_map: StringToNumMap::default(),
+            events_per_name: StableBTreeMap::init(get_events_per_name_memory()),
+            field_values: StableBTreeMap::init(get_field_values_memory()),
+            field_value_counts: StableBTreeMap::init(get_field_value_counts_memory()),
         }
     }
 }
@@ -98,7 +192,12 @@ fn init_events() -> StableLog<StorableEvent, Memory, Memory> {
 }
 
 pub struct EventsStats {
-    pub latest_event_index: Option<u64>,
+    pub event_count: u64,
+    pub string_count: u64,
+    pub distinct_names: u64,
+    pub distinct_users: u64,
+    pub distinct_sources: u64,
+    pub events_per_name: Vec<(String, u64)>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -109,6 +208,10 @@ struct StorableEvent {
     name: u32,
     #[serde(rename = "t")]
     timestamp: TimestampMillis,
+    #[serde(rename = "it", default, skip_serializing_if = "Option::is_none")]
+    ingested_at: Option<TimestampMillis>,
+    #[serde(rename = "sr", default, skip_serializing_if = "Option::is_none")]
+    sample_rate_per_million: Option<u32>,
     #[serde(rename = "u", default, skip_serializing_if = "Option::is_none")]
     user: Option<u32>,
     #[serde(rename = "s", default, skip_serializing_if = "Option::is_none")]
@@ -138,6 +241,26 @@ fn is_empty_slice<T>(vec: &[T]) -> bool {
     vec.is_empty()
 }
 
+// Anonymizes the user and source if required, this is shared by all streams
+pub fn to_indexed_event(
+    event: IdempotentEvent,
+    index: u64,
+    salt: [u8; 32],
+    now: TimestampMillis,
+    sample_rate_per_million: Option<u32>,
+) -> IndexedEvent {
+    IndexedEvent {
+        index,
+        name: event.name,
+        timestamp: event.timestamp,
+        ingested_at: Some(now),
+        sample_rate_per_million,
+        user: event.user.map(|u| to_maybe_anonymized_string(u, salt)),
+        source: event.source.map(|s| to_maybe_anonymized_string(s, salt)),
+        payload: event.payload,
+    }
+}
+
 fn to_maybe_anonymized_string(value: Anonymizable, salt: [u8; 32]) -> String {
     match value {
         Anonymizable::Public(s) => s,
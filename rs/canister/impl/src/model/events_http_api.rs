@@ -0,0 +1,26 @@
+use event_store_canister::EventsHttpApiConfig;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+// Only the hashes of the tokens are stored
+#[derive(Serialize, Deserialize, Default)]
+pub struct EventsHttpApi {
+    public: bool,
+    token_hashes: HashSet<[u8; 32]>,
+}
+
+impl EventsHttpApi {
+    pub fn set_config(&mut self, config: EventsHttpApiConfig) {
+        self.public = config.public;
+        self.token_hashes = config.tokens.iter().map(|t| hash_token(t)).collect();
+    }
+
+    pub fn is_authorized(&self, token: Option<&str>) -> bool {
+        self.public || token.is_some_and(|t| self.token_hashes.contains(&hash_token(t)))
+    }
+}
+
+fn hash_token(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
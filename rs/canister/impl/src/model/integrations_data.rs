@@ -1,4 +1,5 @@
-use event_store_types::IndexedEvent;
+use event_store_canister::DappRadarConfig;
+use event_store_types::{IndexedEvent, TimestampMillis};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Default)]
@@ -7,22 +8,84 @@ pub struct IntegrationsData {
     pub dapp_radar: crate::integrations::dapp_radar::DappRadarData,
 }
 
+#[derive(Clone, Copy, Debug)]
+pub enum Integration {
+    #[cfg(feature = "dapp-radar")]
+    DappRadar,
+}
+
+impl Integration {
+    pub fn all() -> Vec<Integration> {
+        vec![
+            #[cfg(feature = "dapp-radar")]
+            Integration::DappRadar,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "dapp-radar")]
+            Integration::DappRadar => "dapp_radar",
+        }
+    }
+}
+
 #[allow(unused_mut)]
 #[allow(unused_variables)]
 impl IntegrationsData {
-    pub fn push_event(&mut self, event: IndexedEvent) {
+    // Any integration whose config changes has its data cleared, to be rebuilt by the
+    // integrations backfill job
+    pub fn set_config(&mut self, dapp_radar_config: Option<DappRadarConfig>) {
         #[cfg(feature = "dapp-radar")]
-        self.dapp_radar.push_event(&event);
+        if let Some(config) = dapp_radar_config {
+            self.dapp_radar.set_config(config);
+        }
     }
 
-    pub fn next_event_index(&self) -> Option<u64> {
-        let mut index = u64::MAX;
+    // Returns true if any integration's data needs to be rebuilt after an upgrade
+    pub fn migrate_if_required(&mut self) -> bool {
+        let mut migrated = false;
 
         #[cfg(feature = "dapp-radar")]
         {
-            index = std::cmp::min(index, self.dapp_radar.next_event_index());
+            migrated |= self.dapp_radar.migrate_if_required();
         }
 
-        if index == u64::MAX { None } else { Some(index) }
+        migrated
+    }
+
+    pub fn push_event(&mut self, event: IndexedEvent, now: TimestampMillis) {
+        for integration in Integration::all() {
+            self.push_event_to(integration, &event, now);
+        }
+    }
+
+    pub fn push_event_to(
+        &mut self,
+        integration: Integration,
+        event: &IndexedEvent,
+        now: TimestampMillis,
+    ) {
+        match integration {
+            #[cfg(feature = "dapp-radar")]
+            Integration::DappRadar => self.dapp_radar.push_event(event, now),
+        }
+    }
+
+    pub fn next_event_index(&self, integration: Integration) -> u64 {
+        match integration {
+            #[cfg(feature = "dapp-radar")]
+            Integration::DappRadar => self.dapp_radar.next_event_index(),
+        }
+    }
+
+    // Returns the integration which is furthest behind, along with the index of the next event it
+    // needs, or `None` if every integration has processed all events
+    pub fn next_to_backfill(&self, events_count: u64) -> Option<(Integration, u64)> {
+        Integration::all()
+            .into_iter()
+            .map(|i| (i, self.next_event_index(i)))
+            .filter(|(_, next)| *next < events_count)
+            .min_by_key(|(_, next)| *next)
     }
 }
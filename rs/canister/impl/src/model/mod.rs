@@ -1,4 +1,11 @@
+pub mod certified_responses;
+pub mod event_limits;
+pub mod event_name_overrides;
 pub mod events;
+pub mod events_http_api;
 pub mod integrations_data;
+pub mod producers;
 pub mod salt;
+pub mod sampling;
+pub mod streams;
 mod string_to_num_map;
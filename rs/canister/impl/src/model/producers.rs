@@ -0,0 +1,111 @@
+use candid::Principal;
+use event_store_canister::{
+    BatchTooLarge, ProducerLimits, ProducerLimitsConfig, PushEventsResponse, RateLimitExceeded,
+};
+use event_store_types::{Milliseconds, TimestampMillis};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const RATE_LIMIT_WINDOW: Milliseconds = 60 * 1000; // 1 minute
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Producers {
+    producers: BTreeMap<Principal, Producer>,
+    #[serde(default)]
+    default_limits: ProducerLimits,
+    #[serde(default)]
+    limits_per_principal: BTreeMap<Principal, ProducerLimits>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Producer {
+    pub last_push: TimestampMillis,
+    #[serde(default)]
+    pub events: u64,
+    #[serde(default)]
+    pub bytes: u64,
+    #[serde(default)]
+    pub rejected_batches: u64,
+    #[serde(default)]
+    rate_limit_window_start: TimestampMillis,
+    #[serde(default)]
+    events_in_rate_limit_window: u64,
+}
+
+impl Producers {
+    pub fn set_limits(&mut self, config: ProducerLimitsConfig) {
+        self.default_limits = config.default;
+        self.limits_per_principal = config.per_principal.into_iter().collect();
+    }
+
+    // Checks the batch against the principal's limits, then, if it is within them, records the
+    // push. Rejected batches are counted but their events and bytes are not.
+    pub fn try_record_push(
+        &mut self,
+        principal: Principal,
+        events: u64,
+        bytes: u64,
+        now: TimestampMillis,
+    ) -> Result<(), PushEventsResponse> {
+        let limits = self
+            .limits_per_principal
+            .get(&principal)
+            .copied()
+            .unwrap_or(self.default_limits);
+
+        let producer = self.producers.entry(principal).or_default();
+
+        if let Err(response) = producer.check_limits(limits, events, now) {
+            producer.rejected_batches += 1;
+            return Err(response);
+        }
+
+        producer.last_push = now;
+        producer.events += events;
+        producer.bytes += bytes;
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Principal, &Producer)> {
+        self.producers.iter()
+    }
+}
+
+impl Producer {
+    fn check_limits(
+        &mut self,
+        limits: ProducerLimits,
+        events: u64,
+        now: TimestampMillis,
+    ) -> Result<(), PushEventsResponse> {
+        // A batch larger than the rate limit could never be accepted, so it is treated as being
+        // too large
+        let max_batch_size = match (limits.max_batch_size, limits.max_events_per_minute) {
+            (Some(b), Some(r)) => Some((b as u64).min(r)),
+            (b, r) => b.map(|b| b as u64).or(r),
+        };
+
+        if let Some(max_batch_size) = max_batch_size {
+            if events > max_batch_size {
+                return Err(PushEventsResponse::BatchTooLarge(BatchTooLarge {
+                    max_batch_size: max_batch_size.try_into().unwrap_or(u32::MAX),
+                }));
+            }
+        }
+
+        if let Some(max_events_per_minute) = limits.max_events_per_minute {
+            let window_end = self.rate_limit_window_start + RATE_LIMIT_WINDOW;
+            if now >= window_end {
+                self.rate_limit_window_start = now;
+                self.events_in_rate_limit_window = 0;
+            } else if self.events_in_rate_limit_window + events > max_events_per_minute {
+                return Err(PushEventsResponse::RateLimitExceeded(RateLimitExceeded {
+                    retry_after: window_end - now,
+                }));
+            }
+            self.events_in_rate_limit_window += events;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use crate::model::event_name_overrides::EventNameOverrides;
+use event_store_canister::{EventNameMatcher, SampleRate};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const ONE_MILLION: u32 = 1_000_000;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Sampling {
+    sample_rates: EventNameOverrides<u32>,
+    sampled_out: u64,
+}
+
+impl Sampling {
+    pub fn set(&mut self, event_name: EventNameMatcher, sample_rate_per_million: u32) {
+        self.sample_rates
+            .set(event_name, sample_rate_per_million.min(ONE_MILLION));
+    }
+
+    pub fn remove(&mut self, event_name: &EventNameMatcher) -> bool {
+        self.sample_rates.remove(event_name)
+    }
+
+    // Returns whether the event should be kept, along with the sample rate to store with it if
+    // its name is being sampled
+    pub fn sample(&mut self, name: &str, idempotency_key: u128) -> (bool, Option<u32>) {
+        let Some(sample_rate) = self.sample_rates.get(name).copied() else {
+            return (true, None);
+        };
+
+        let hash: [u8; 32] = Sha256::digest(idempotency_key.to_be_bytes()).into();
+        let bucket = u64::from_be_bytes(hash[..8].try_into().unwrap()) % ONE_MILLION as u64;
+        let keep = bucket < sample_rate as u64;
+
+        if !keep {
+            self.sampled_out += 1;
+        }
+        (keep, Some(sample_rate))
+    }
+
+    pub fn sampled_out(&self) -> u64 {
+        self.sampled_out
+    }
+
+    pub fn sample_rates(&self) -> Vec<SampleRate> {
+        self.sample_rates
+            .iter()
+            .map(|(event_name, sample_rate)| SampleRate {
+                event_name,
+                sample_rate_per_million: *sample_rate,
+            })
+            .collect()
+    }
+}
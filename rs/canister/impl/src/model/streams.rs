@@ -0,0 +1,239 @@
+use crate::memory::{Memory, STREAM_MEMORIES_END, STREAM_MEMORIES_START, get_stream_memory};
+use crate::model::events::to_indexed_event;
+use candid::Principal;
+use event_store_canister::{SetStreamResponse, StreamConfig, StreamSummary, TooManyStreams};
+use event_store_types::{IdempotentEvent, IndexedEvent, TimestampMillis};
+use event_store_utils::EventDeduper;
+use ic_stable_structures::storable::Bound;
+use ic_stable_structures::{StableBTreeMap, Storable};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+const MAX_NAME_LEN: usize = 64;
+// Pruning is spread across pushes so that no single push has to remove a large number of events
+const MAX_EVENTS_PRUNED_PER_PUSH: u64 = 10;
+
+// Named streams, each with its own index sequence, whitelists and retention. Events pushed
+// without a stream go into the default stream, which is held in `Events`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Streams {
+    streams: BTreeMap<String, Stream>,
+}
+
+// Only the stream's metadata is serialized, its events live in the stream's own stable memory
+#[derive(Serialize, Deserialize)]
+#[serde(from = "StreamMetadata")]
+pub struct Stream {
+    memory_id: u8,
+    config: StreamConfig,
+    next_index: u64,
+    // Idempotency keys are deduplicated per stream, so the same key may be pushed to each stream
+    event_deduper: EventDeduper,
+    #[serde(skip_serializing)]
+    events: StableBTreeMap<u64, StorableStreamEvent, Memory>,
+}
+
+#[derive(Deserialize)]
+struct StreamMetadata {
+    memory_id: u8,
+    config: StreamConfig,
+    next_index: u64,
+    #[serde(default)]
+    event_deduper: EventDeduper,
+}
+
+impl Streams {
+    pub fn max_streams() -> u32 {
+        (STREAM_MEMORIES_END - STREAM_MEMORIES_START) as u32 + 1
+    }
+
+    // Stable memories can't be freed, so streams are never removed and each new stream takes the
+    // next unused memory
+    pub fn set(&mut self, name: String, config: StreamConfig) -> SetStreamResponse {
+        if !is_valid_name(&name) {
+            return SetStreamResponse::InvalidName;
+        }
+
+        if let Some(stream) = self.streams.get_mut(&name) {
+            stream.config = config;
+            return SetStreamResponse::Success;
+        }
+
+        let max = Self::max_streams();
+        if self.streams.len() as u32 >= max {
+            return SetStreamResponse::TooManyStreams(TooManyStreams { max });
+        }
+
+        let memory_id = STREAM_MEMORIES_START + self.streams.len() as u8;
+        self.streams.insert(
+            name,
+            Stream::from(StreamMetadata {
+                memory_id,
+                config,
+                next_index: 0,
+                event_deduper: EventDeduper::default(),
+            }),
+        );
+        SetStreamResponse::Success
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Stream> {
+        self.streams.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Stream> {
+        self.streams.get_mut(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Stream)> {
+        self.streams.iter()
+    }
+
+    pub fn prune_required(&self, now: TimestampMillis) -> bool {
+        self.streams.values().any(|s| s.is_first_event_expired(now))
+    }
+
+    // Removes up to `max_events` expired events across all streams, returning how many were removed
+    pub fn prune(&mut self, now: TimestampMillis, max_events: u64) -> u64 {
+        let mut removed = 0;
+        for stream in self.streams.values_mut() {
+            removed += stream.prune(now, max_events - removed);
+            if removed >= max_events {
+                break;
+            }
+        }
+        removed
+    }
+
+    pub fn summaries(&self) -> Vec<StreamSummary> {
+        self.streams
+            .iter()
+            .map(|(name, stream)| StreamSummary {
+                name: name.clone(),
+                config: stream.config.clone(),
+                event_count: stream.count(),
+                first_event_index: stream.first_event_index(),
+                latest_event_index: stream.latest_event_index(),
+            })
+            .collect()
+    }
+}
+
+impl Stream {
+    pub fn memory_id(&self) -> u8 {
+        self.memory_id
+    }
+
+    pub fn can_push(&self, principal: Principal) -> bool {
+        self.config.push_events_whitelist.contains(&principal)
+    }
+
+    pub fn can_read(&self, principal: Principal) -> bool {
+        self.config.read_events_whitelist.contains(&principal)
+    }
+
+    // Events which have been removed due to retention are skipped, so the first event returned
+    // may have a higher index than `start`
+    pub fn get(&self, start: u64, length: u64) -> Vec<IndexedEvent> {
+        self.events
+            .range(start..)
+            .take(length as usize)
+            .map(|(_, e)| e.0)
+            .collect()
+    }
+
+    pub fn event_deduper_mut(&mut self) -> &mut EventDeduper {
+        &mut self.event_deduper
+    }
+
+    pub fn push(
+        &mut self,
+        event: IdempotentEvent,
+        salt: [u8; 32],
+        now: TimestampMillis,
+        sample_rate_per_million: Option<u32>,
+    ) -> IndexedEvent {
+        let indexed = to_indexed_event(event, self.next_index, salt, now, sample_rate_per_million);
+        self.events
+            .insert(indexed.index, StorableStreamEvent(indexed.clone()));
+        self.next_index += 1;
+        self.prune(now, MAX_EVENTS_PRUNED_PER_PUSH);
+        indexed
+    }
+
+    pub fn count(&self) -> u64 {
+        self.events.len()
+    }
+
+    pub fn first_event_index(&self) -> Option<u64> {
+        self.events.first_key_value().map(|(index, _)| index)
+    }
+
+    pub fn latest_event_index(&self) -> Option<u64> {
+        self.next_index.checked_sub(1)
+    }
+
+    fn prune(&mut self, now: TimestampMillis, max_events: u64) -> u64 {
+        let mut removed = 0;
+        while removed < max_events && self.is_first_event_expired(now) {
+            self.events.pop_first();
+            removed += 1;
+        }
+        removed
+    }
+
+    fn is_first_event_expired(&self, now: TimestampMillis) -> bool {
+        let retention = self.config.retention;
+        if retention
+            .max_events
+            .is_some_and(|max| self.events.len() > max)
+        {
+            return true;
+        }
+
+        if let Some(max_age) = retention.max_age {
+            if let Some((_, event)) = self.events.first_key_value() {
+                return event
+                    .0
+                    .ingested_at
+                    .is_some_and(|ingested_at| now.saturating_sub(ingested_at) > max_age);
+            }
+        }
+        false
+    }
+}
+
+impl From<StreamMetadata> for Stream {
+    fn from(metadata: StreamMetadata) -> Self {
+        Stream {
+            memory_id: metadata.memory_id,
+            config: metadata.config,
+            next_index: metadata.next_index,
+            event_deduper: metadata.event_deduper,
+            events: StableBTreeMap::init(get_stream_memory(metadata.memory_id)),
+        }
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+struct StorableStreamEvent(IndexedEvent);
+
+impl Storable for StorableStreamEvent {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(rmp_serde::to_vec_named(&self.0).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        StorableStreamEvent(rmp_serde::from_slice(bytes.as_ref()).unwrap())
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
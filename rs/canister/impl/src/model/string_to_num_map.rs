@@ -21,9 +21,17 @@ impl StringToNumMap {
         }
     }
 
+    pub fn get_num(&self, string: &str) -> Option<u32> {
+        self.string_to_num.get(&string.to_string())
+    }
+
     pub fn convert_to_string(&self, num: u32) -> Option<String> {
         self.num_to_string.get(num as u64)
     }
+
+    pub fn len(&self) -> u64 {
+        self.num_to_string.len()
+    }
 }
 
 impl Default for StringToNumMap {
@@ -5,13 +5,16 @@ use ic_cdk::query;
 
 #[query(guard = "caller_can_read_events")]
 fn events(args: EventsArgs) -> EventsResponse {
-    state::read(|s| {
-        let stats = s.events().stats();
-        let events = s.events().get(args.start, args.length);
-
-        EventsResponse {
-            events,
-            latest_event_index: stats.latest_event_index,
-        }
-    })
+    state::read(
+        |s| match args.stream.and_then(|name| s.streams().get(&name)) {
+            Some(stream) => EventsResponse {
+                events: stream.get(args.start, args.length),
+                latest_event_index: stream.latest_event_index(),
+            },
+            None => EventsResponse {
+                events: s.events().get(args.start, args.length),
+                latest_event_index: s.events().latest_event_index(),
+            },
+        },
+    )
 }
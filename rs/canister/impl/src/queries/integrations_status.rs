@@ -0,0 +1,8 @@
+use crate::state;
+use event_store_canister::IntegrationsStatusResponse;
+use ic_cdk::query;
+
+#[query]
+fn integrations_status() -> IntegrationsStatusResponse {
+    state::read(|s| s.integrations_status())
+}
@@ -1,3 +1,8 @@
 mod events;
 mod http_request;
+mod integrations_status;
+mod sample_rates;
+mod stats;
+mod streams;
+mod time_granularity;
 mod whitelisted_principals;
@@ -0,0 +1,10 @@
+use crate::state;
+use event_store_canister::SampleRatesResponse;
+use ic_cdk::query;
+
+#[query]
+fn sample_rates() -> SampleRatesResponse {
+    state::read(|s| SampleRatesResponse {
+        sample_rates: s.sampling().sample_rates(),
+    })
+}
@@ -0,0 +1,8 @@
+use crate::state;
+use event_store_canister::StatsResponse;
+use ic_cdk::query;
+
+#[query]
+fn stats() -> StatsResponse {
+    state::read(|s| s.stats())
+}
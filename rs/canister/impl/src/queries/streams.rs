@@ -0,0 +1,10 @@
+use crate::state;
+use event_store_canister::StreamsResponse;
+use ic_cdk::query;
+
+#[query]
+fn streams() -> StreamsResponse {
+    state::read(|s| StreamsResponse {
+        streams: s.streams().summaries(),
+    })
+}
@@ -0,0 +1,8 @@
+use crate::state;
+use event_store_canister::TimeGranularityResponse;
+use ic_cdk::query;
+
+#[query]
+fn time_granularity() -> TimeGranularityResponse {
+    state::read(|s| s.time_granularity())
+}
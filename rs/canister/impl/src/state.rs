@@ -1,14 +1,25 @@
-use crate::env;
+use crate::model::certified_responses::CertifiedResponses;
+use crate::model::event_limits::{EventLimits, violation_reason};
+use crate::model::event_name_overrides::EventNameOverrides;
 use crate::model::events::Events;
-use crate::model::integrations_data::IntegrationsData;
+use crate::model::events_http_api::EventsHttpApi;
+use crate::model::integrations_data::{Integration, IntegrationsData};
+use crate::model::producers::Producers;
 use crate::model::salt::Salt;
+use crate::model::sampling::Sampling;
+use crate::model::streams::Streams;
+use crate::{env, memory};
 use candid::Principal;
-use event_store_canister::WhitelistedPrincipals;
+use event_store_canister::{
+    EventNameMatcher, EventNameStats, EventViolation, IntegrationStatus,
+    IntegrationsStatusResponse, MemoryStats, ProducerStats, PushEventsResponse, StatsResponse,
+    TimeGranularityOverride, TimeGranularityResponse, TimestampPolicy, WhitelistedPrincipals,
+};
 use event_store_types::{IdempotentEvent, Milliseconds, TimestampMillis};
 use event_store_utils::EventDeduper;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 thread_local! {
     static STATE: RefCell<Option<State>> = RefCell::default();
@@ -19,12 +30,35 @@ pub struct State {
     push_events_whitelist: HashSet<Principal>,
     read_events_whitelist: HashSet<Principal>,
     time_granularity: Option<Milliseconds>,
+    #[serde(default)]
+    time_granularity_overrides: EventNameOverrides<Option<Milliseconds>>,
     #[serde(skip)]
     events: Events,
     event_deduper: EventDeduper,
     #[serde(default)]
     integrations_data: IntegrationsData,
     salt: Salt,
+    #[serde(default)]
+    events_http_api: EventsHttpApi,
+    #[serde(default)]
+    metrics_http_api: EventsHttpApi,
+    #[serde(default)]
+    producers: Producers,
+    #[serde(default)]
+    event_limits: EventLimits,
+    #[serde(default)]
+    timestamp_policy: TimestampPolicy,
+    #[serde(default)]
+    sampling: Sampling,
+    #[serde(default)]
+    streams: Streams,
+    #[serde(default)]
+    rejected_events_per_reason: BTreeMap<String, u64>,
+    // Events below this index have had their field values recorded
+    #[serde(default)]
+    field_values_backfilled_up_to: u64,
+    #[serde(skip)]
+    certified_responses: CertifiedResponses,
 }
 
 const STATE_ALREADY_INITIALIZED: &str = "State has already been initialized";
@@ -61,22 +95,39 @@ impl State {
         State {
             push_events_whitelist,
             read_events_whitelist,
-            time_granularity,
+            time_granularity: time_granularity.filter(|g| *g > 0),
+            time_granularity_overrides: EventNameOverrides::default(),
             events: Events::default(),
             event_deduper: EventDeduper::default(),
             integrations_data: IntegrationsData::default(),
             salt: Salt::default(),
+            events_http_api: EventsHttpApi::default(),
+            metrics_http_api: EventsHttpApi::default(),
+            producers: Producers::default(),
+            field_values_backfilled_up_to: 0,
+            event_limits: EventLimits::default(),
+            rejected_events_per_reason: BTreeMap::new(),
+            timestamp_policy: TimestampPolicy::default(),
+            sampling: Sampling::default(),
+            streams: Streams::default(),
+            certified_responses: CertifiedResponses::default(),
         }
     }
 
-    pub fn can_caller_push_events(&self) -> bool {
+    pub fn can_caller_push_to_stream(&self, stream: Option<&str>) -> bool {
         let caller = env::caller();
-        self.push_events_whitelist.contains(&caller)
+        match stream {
+            None => self.push_events_whitelist.contains(&caller),
+            Some(name) => self.streams.get(name).is_some_and(|s| s.can_push(caller)),
+        }
     }
 
-    pub fn can_caller_read_events(&self) -> bool {
+    pub fn can_caller_read_from_stream(&self, stream: Option<&str>) -> bool {
         let caller = env::caller();
-        self.read_events_whitelist.contains(&caller)
+        match stream {
+            None => self.read_events_whitelist.contains(&caller),
+            Some(name) => self.streams.get(name).is_some_and(|s| s.can_read(caller)),
+        }
     }
 
     pub fn whitelisted_principals(&self) -> WhitelistedPrincipals {
@@ -90,31 +141,341 @@ impl State {
         &self.events
     }
 
+    pub fn streams(&self) -> &Streams {
+        &self.streams
+    }
+
+    pub fn streams_mut(&mut self) -> &mut Streams {
+        &mut self.streams
+    }
+
+    pub fn event_deduper_len(&self) -> usize {
+        self.event_deduper.len()
+    }
+
+    pub fn events_http_api(&self) -> &EventsHttpApi {
+        &self.events_http_api
+    }
+
+    pub fn events_http_api_mut(&mut self) -> &mut EventsHttpApi {
+        &mut self.events_http_api
+    }
+
+    pub fn metrics_http_api(&self) -> &EventsHttpApi {
+        &self.metrics_http_api
+    }
+
+    pub fn metrics_http_api_mut(&mut self) -> &mut EventsHttpApi {
+        &mut self.metrics_http_api
+    }
+
     pub fn set_salt(&mut self, salt: [u8; 32]) {
         self.salt.set(salt);
     }
 
-    pub fn push_event(&mut self, mut event: IdempotentEvent, now: TimestampMillis) {
-        if self.event_deduper.try_push(event.idempotency_key, now) {
-            if let Some(granularity) = self.time_granularity {
+    pub fn event_limits(&self) -> &EventLimits {
+        &self.event_limits
+    }
+
+    pub fn rejected_events_per_reason(&self) -> &BTreeMap<String, u64> {
+        &self.rejected_events_per_reason
+    }
+
+    pub fn time_granularity(&self) -> TimeGranularityResponse {
+        TimeGranularityResponse {
+            default: self.time_granularity,
+            overrides: self
+                .time_granularity_overrides
+                .iter()
+                .map(|(event_name, time_granularity)| TimeGranularityOverride {
+                    event_name,
+                    time_granularity: *time_granularity,
+                })
+                .collect(),
+        }
+    }
+
+    // A granularity of zero is treated as `None`, since timestamps can't be rounded down to a
+    // multiple of zero
+    pub fn set_time_granularity_override(
+        &mut self,
+        event_name: EventNameMatcher,
+        time_granularity: Option<Milliseconds>,
+    ) {
+        self.time_granularity_overrides
+            .set(event_name, time_granularity.filter(|g| *g > 0));
+    }
+
+    pub fn remove_time_granularity_override(&mut self, event_name: &EventNameMatcher) -> bool {
+        self.time_granularity_overrides.remove(event_name)
+    }
+
+    pub fn sampling(&self) -> &Sampling {
+        &self.sampling
+    }
+
+    pub fn sampling_mut(&mut self) -> &mut Sampling {
+        &mut self.sampling
+    }
+
+    pub fn set_timestamp_policy(&mut self, timestamp_policy: TimestampPolicy) {
+        self.timestamp_policy = timestamp_policy;
+    }
+
+    pub fn event_limits_mut(&mut self) -> &mut EventLimits {
+        &mut self.event_limits
+    }
+
+    // Events which exceed the size limits are rejected, returning the limits they violated.
+    // Events are pushed to the default stream if `stream` is None. Named streams don't feed the
+    // integrations or the field value tracking.
+    pub fn push_event(
+        &mut self,
+        mut event: IdempotentEvent,
+        stream: Option<&str>,
+        now: TimestampMillis,
+    ) -> Result<(), Vec<EventViolation>> {
+        let violations = self.event_limits.validate(
+            &event,
+            stream.is_none().then_some(&self.events),
+            !self.field_values_backfill_required(),
+        );
+        if !violations.is_empty() {
+            for violation in violations.iter() {
+                *self
+                    .rejected_events_per_reason
+                    .entry(violation_reason(violation).to_string())
+                    .or_default() += 1;
+            }
+            return Err(violations);
+        }
+
+        // Each stream has its own deduper, so idempotency keys only need to be unique per stream
+        let event_deduper = match stream {
+            Some(name) => match self.streams.get_mut(name) {
+                Some(stream) => stream.event_deduper_mut(),
+                None => return Ok(()),
+            },
+            None => &mut self.event_deduper,
+        };
+
+        if event_deduper.try_push(event.idempotency_key, now) {
+            let (keep, sample_rate) = self.sampling.sample(&event.name, event.idempotency_key);
+            if !keep {
+                return Ok(());
+            }
+
+            event.timestamp = self.timestamp_policy.apply(event.timestamp, now);
+            let time_granularity = self
+                .time_granularity_overrides
+                .get(&event.name)
+                .copied()
+                .unwrap_or(self.time_granularity);
+
+            if let Some(granularity) = time_granularity {
                 event.timestamp = event
                     .timestamp
                     .saturating_sub(event.timestamp % granularity);
             }
 
-            let indexed_event = self.events.push(event, self.salt.get());
+            if let Some(name) = stream {
+                if let Some(stream) = self.streams.get_mut(name) {
+                    stream.push(event, self.salt.get(), now, sample_rate);
+                }
+                return Ok(());
+            }
+
+            // While the backfill is in progress, new events are left for it to process
+            let backfilled = !self.field_values_backfill_required();
+            let indexed_event =
+                self.events
+                    .push(event, self.salt.get(), now, sample_rate, backfilled);
+            if backfilled {
+                self.field_values_backfilled_up_to += 1;
+            }
 
-            self.integrations_data.push_event(indexed_event);
+            self.integrations_data.push_event(indexed_event, now);
         }
+        Ok(())
+    }
+
+    pub fn try_record_push(
+        &mut self,
+        caller: Principal,
+        events: u64,
+        bytes: u64,
+        now: TimestampMillis,
+    ) -> Result<(), PushEventsResponse> {
+        self.producers.try_record_push(caller, events, bytes, now)
+    }
+
+    pub fn producers_mut(&mut self) -> &mut Producers {
+        &mut self.producers
+    }
+
+    pub fn field_values_backfill_required(&self) -> bool {
+        self.field_values_backfilled_up_to < self.events.count()
+    }
+
+    pub fn backfill_field_values(&mut self, count: u64) {
+        self.field_values_backfilled_up_to = self
+            .events
+            .backfill_field_values(self.field_values_backfilled_up_to, count);
     }
 
-    #[allow(dead_code)]
     pub fn integrations_data(&self) -> &IntegrationsData {
         &self.integrations_data
     }
 
-    #[allow(dead_code)]
     pub fn integrations_data_mut(&mut self) -> &mut IntegrationsData {
         &mut self.integrations_data
     }
+
+    pub fn integrations_backfill_required(&self) -> bool {
+        self.integrations_data
+            .next_to_backfill(self.events.count())
+            .is_some()
+    }
+
+    pub fn integrations_status(&self) -> IntegrationsStatusResponse {
+        let events_count = self.events.count();
+
+        IntegrationsStatusResponse {
+            latest_event_index: events_count.checked_sub(1),
+            integrations: Integration::all()
+                .into_iter()
+                .map(|i| {
+                    let next_event_index = self.integrations_data.next_event_index(i);
+                    IntegrationStatus {
+                        name: i.name().to_string(),
+                        next_event_index,
+                        events_remaining: events_count.saturating_sub(next_event_index),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn stats(&self) -> StatsResponse {
+        let events_stats = self.events.stats();
+
+        StatsResponse {
+            total_events: events_stats.event_count,
+            latest_event_index: self.events.latest_event_index(),
+            events_per_name: events_stats
+                .events_per_name
+                .into_iter()
+                .map(|(name, count)| EventNameStats { name, count })
+                .collect(),
+            distinct_strings: events_stats.string_count,
+            distinct_names: events_stats.distinct_names,
+            distinct_users: events_stats.distinct_users,
+            distinct_sources: events_stats.distinct_sources,
+            distinct_values_backfilled_up_to: self.field_values_backfilled_up_to,
+            stable_memory_bytes: env::stable_memory_bytes(),
+            heap_memory_bytes: env::heap_memory_bytes(),
+            memory: memory::memory_sizes()
+                .into_iter()
+                .map(|(memory_id, name, bytes)| MemoryStats {
+                    memory_id,
+                    name: name.to_string(),
+                    bytes,
+                })
+                .chain(self.streams.iter().map(|(name, stream)| MemoryStats {
+                    memory_id: stream.memory_id(),
+                    name: format!("stream_{name}"),
+                    bytes: memory::memory_size(stream.memory_id()),
+                }))
+                .collect(),
+            dedup_window_duration: self.event_deduper.window_duration(),
+            dedup_keys: self.event_deduper.len() as u64,
+            salt_initialized: self.salt.is_initialized(),
+            integrations: self.integrations_status(),
+            producers: self
+                .producers
+                .iter()
+                .map(|(principal, producer)| ProducerStats {
+                    principal: *principal,
+                    last_push: producer.last_push,
+                    events: producer.events,
+                    bytes: producer.bytes,
+                    rejected_batches: producer.rejected_batches,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn certified_responses(&self) -> &CertifiedResponses {
+        &self.certified_responses
+    }
+
+    // Certifications are not persisted across upgrades, so everything which can be certified is
+    // queued up to be certified again
+    pub fn init_certified_responses(&mut self) {
+        #[cfg(feature = "dapp-radar")]
+        self.integrations_data
+            .dapp_radar
+            .queue_views_for_certification();
+
+        self.certified_responses.update_certified_data();
+    }
+
+    // Removes the certifications of any responses whose data has changed. This must be called
+    // within the same update as the data was changed in, otherwise the responses would no longer
+    // match their certifications.
+    pub fn invalidate_certified_responses(&mut self) {
+        #[allow(unused_mut)]
+        let mut any_removed = false;
+
+        #[cfg(feature = "dapp-radar")]
+        for (date, view) in self.integrations_data.dapp_radar.take_invalidated_views() {
+            self.certified_responses
+                .remove(&crate::http::dapp_radar::certified_path(date, view));
+            any_removed = true;
+        }
+
+        if any_removed {
+            self.certified_responses.update_certified_data();
+        }
+    }
+
+    // Certifies the responses of the next route which is ready to be certified, returning false
+    // if there are none. `update_certified_data` must be called once certification is complete.
+    #[allow(unused_variables)]
+    pub fn certify_next_http_route(&mut self, now: TimestampMillis) -> bool {
+        #[cfg(feature = "dapp-radar")]
+        if let Some((date, view)) = self.integrations_data.dapp_radar.next_view_to_certify(now) {
+            let route = crate::http::dapp_radar::certifiable_route(self, date, view);
+            let responses = route
+                .requests
+                .iter()
+                .map(|r| crate::http::handle_request(self, r))
+                .collect();
+
+            self.certified_responses.certify(route, responses);
+            self.integrations_data.dapp_radar.mark_certified(date, view);
+            return true;
+        }
+
+        false
+    }
+
+    #[allow(unused_variables)]
+    pub fn has_http_routes_to_certify(&mut self, now: TimestampMillis) -> bool {
+        #[cfg(feature = "dapp-radar")]
+        if self
+            .integrations_data
+            .dapp_radar
+            .next_view_to_certify(now)
+            .is_some()
+        {
+            return true;
+        }
+
+        false
+    }
+
+    pub fn update_certified_data(&self) {
+        self.certified_responses.update_certified_data();
+    }
 }
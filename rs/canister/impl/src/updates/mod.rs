@@ -1 +1,6 @@
 mod push_events;
+mod remove_sample_rate;
+mod remove_time_granularity_override;
+mod set_sample_rate;
+mod set_stream;
+mod set_time_granularity_override;
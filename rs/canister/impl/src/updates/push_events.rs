@@ -1,15 +1,36 @@
 use crate::guards::caller_can_push_events;
 use crate::{env, state};
-use event_store_canister::PushEventsArgs;
+use event_store_canister::{PartialSuccess, PushEventsArgs, PushEventsResponse, RejectedEvent};
 use ic_cdk::update;
 
 #[update(guard = "caller_can_push_events")]
-fn push_events(args: PushEventsArgs) {
+fn push_events(args: PushEventsArgs) -> PushEventsResponse {
     let now = env::time();
+    let caller = env::caller();
+    let bytes = env::arg_data_size();
 
     state::mutate(|s| {
-        for event in args.events {
-            s.push_event(event, now);
+        if let Err(response) = s.try_record_push(caller, args.events.len() as u64, bytes, now) {
+            return response;
         }
-    });
+
+        let mut rejected_events = Vec::new();
+        for (index, event) in args.events.into_iter().enumerate() {
+            let idempotency_key = event.idempotency_key;
+            if let Err(violations) = s.push_event(event, args.stream.as_deref(), now) {
+                rejected_events.push(RejectedEvent {
+                    index: index as u32,
+                    idempotency_key,
+                    violations,
+                });
+            }
+        }
+        s.invalidate_certified_responses();
+
+        if rejected_events.is_empty() {
+            PushEventsResponse::Success
+        } else {
+            PushEventsResponse::PartialSuccess(PartialSuccess { rejected_events })
+        }
+    })
 }
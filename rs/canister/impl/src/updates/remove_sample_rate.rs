@@ -0,0 +1,9 @@
+use crate::guards::caller_is_controller;
+use crate::state;
+use event_store_canister::RemoveSampleRateArgs;
+use ic_cdk::update;
+
+#[update(guard = "caller_is_controller")]
+fn remove_sample_rate(args: RemoveSampleRateArgs) -> bool {
+    state::mutate(|s| s.sampling_mut().remove(&args.event_name))
+}
@@ -0,0 +1,9 @@
+use crate::guards::caller_is_controller;
+use crate::state;
+use event_store_canister::RemoveTimeGranularityOverrideArgs;
+use ic_cdk::update;
+
+#[update(guard = "caller_is_controller")]
+fn remove_time_granularity_override(args: RemoveTimeGranularityOverrideArgs) -> bool {
+    state::mutate(|s| s.remove_time_granularity_override(&args.event_name))
+}
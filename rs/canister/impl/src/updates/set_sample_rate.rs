@@ -0,0 +1,12 @@
+use crate::guards::caller_is_controller;
+use crate::state;
+use event_store_canister::SetSampleRateArgs;
+use ic_cdk::update;
+
+#[update(guard = "caller_is_controller")]
+fn set_sample_rate(args: SetSampleRateArgs) {
+    state::mutate(|s| {
+        s.sampling_mut()
+            .set(args.event_name, args.sample_rate_per_million)
+    });
+}
@@ -0,0 +1,11 @@
+use crate::guards::caller_is_controller;
+use crate::{jobs, state};
+use event_store_canister::{SetStreamArgs, SetStreamResponse};
+use ic_cdk::update;
+
+#[update(guard = "caller_is_controller")]
+fn set_stream(args: SetStreamArgs) -> SetStreamResponse {
+    let response = state::mutate(|s| s.streams_mut().set(args.name, args.config));
+    jobs::prune_streams::start_job_if_required();
+    response
+}
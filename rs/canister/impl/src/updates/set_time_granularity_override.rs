@@ -0,0 +1,9 @@
+use crate::guards::caller_is_controller;
+use crate::state;
+use event_store_canister::SetTimeGranularityOverrideArgs;
+use ic_cdk::update;
+
+#[update(guard = "caller_is_controller")]
+fn set_time_granularity_override(args: SetTimeGranularityOverrideArgs) {
+    state::mutate(|s| s.set_time_granularity_override(args.event_name, args.time_granularity));
+}
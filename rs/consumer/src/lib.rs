@@ -18,7 +18,32 @@ impl<R> EventStoreClient<R> {
 impl<R: Runtime> EventStoreClient<R> {
     pub async fn events(&self, start: u64, length: u64) -> Result<EventsResponse, (i32, String)> {
         self.runtime
-            .events(self.event_store_canister_id, EventsArgs { start, length })
+            .events(
+                self.event_store_canister_id,
+                EventsArgs {
+                    start,
+                    length,
+                    stream: None,
+                },
+            )
+            .await
+    }
+
+    pub async fn stream_events(
+        &self,
+        stream: impl Into<String>,
+        start: u64,
+        length: u64,
+    ) -> Result<EventsResponse, (i32, String)> {
+        self.runtime
+            .events(
+                self.event_store_canister_id,
+                EventsArgs {
+                    start,
+                    length,
+                    stream: Some(stream.into()),
+                },
+            )
             .await
     }
 }
@@ -1,5 +1,10 @@
 use candid::{CandidType, Principal};
-use event_store_canister::{EventsArgs, EventsResponse, PushEventsArgs};
+use event_store_canister::{
+    EventsArgs, EventsResponse, IntegrationsStatusResponse, PushEventsArgs, PushEventsResponse,
+    SetSampleRateArgs, SetStreamArgs, SetStreamResponse, SetTimeGranularityOverrideArgs,
+    StatsResponse,
+};
+use ic_http_certification::{HttpRequest, HttpResponse};
 use pocket_ic::{PocketIc, RejectResponse};
 use serde::de::DeserializeOwned;
 
@@ -12,13 +17,78 @@ pub fn events(
     execute_query(env, sender, canister_id, "events", args)
 }
 
+pub fn http_request(
+    env: &PocketIc,
+    canister_id: Principal,
+    request: &HttpRequest,
+) -> HttpResponse<'static> {
+    execute_query(
+        env,
+        Principal::anonymous(),
+        canister_id,
+        "http_request",
+        request,
+    )
+}
+
+pub fn stats(env: &PocketIc, canister_id: Principal) -> StatsResponse {
+    unwrap_response(env.query_call(
+        canister_id,
+        Principal::anonymous(),
+        "stats",
+        candid::encode_args(()).unwrap(),
+    ))
+}
+
+pub fn integrations_status(env: &PocketIc, canister_id: Principal) -> IntegrationsStatusResponse {
+    unwrap_response(env.query_call(
+        canister_id,
+        Principal::anonymous(),
+        "integrations_status",
+        candid::encode_args(()).unwrap(),
+    ))
+}
+
 pub fn push_events(
     env: &mut PocketIc,
     sender: Principal,
     canister_id: Principal,
     args: &PushEventsArgs,
+) -> PushEventsResponse {
+    execute_update(env, sender, canister_id, "push_events", args)
+}
+
+pub fn set_sample_rate(
+    env: &mut PocketIc,
+    sender: Principal,
+    canister_id: Principal,
+    args: &SetSampleRateArgs,
+) {
+    execute_update(env, sender, canister_id, "set_sample_rate", args)
+}
+
+pub fn set_stream(
+    env: &mut PocketIc,
+    sender: Principal,
+    canister_id: Principal,
+    args: &SetStreamArgs,
+) -> SetStreamResponse {
+    execute_update(env, sender, canister_id, "set_stream", args)
+}
+
+pub fn set_time_granularity_override(
+    env: &mut PocketIc,
+    sender: Principal,
+    canister_id: Principal,
+    args: &SetTimeGranularityOverrideArgs,
 ) {
-    execute_update_no_response(env, sender, canister_id, "push_events", args)
+    execute_update(
+        env,
+        sender,
+        canister_id,
+        "set_time_granularity_override",
+        args,
+    )
 }
 
 fn execute_query<P: CandidType, R: CandidType + DeserializeOwned>(
@@ -36,7 +106,6 @@ fn execute_query<P: CandidType, R: CandidType + DeserializeOwned>(
     ))
 }
 
-#[allow(dead_code)]
 fn execute_update<P: CandidType, R: CandidType + DeserializeOwned>(
     env: &mut PocketIc,
     sender: Principal,
@@ -52,22 +121,6 @@ fn execute_update<P: CandidType, R: CandidType + DeserializeOwned>(
     ))
 }
 
-fn execute_update_no_response<P: CandidType>(
-    env: &mut PocketIc,
-    sender: Principal,
-    canister_id: Principal,
-    method_name: &str,
-    payload: &P,
-) {
-    env.update_call(
-        canister_id,
-        sender,
-        method_name,
-        candid::encode_one(payload).unwrap(),
-    )
-    .unwrap();
-}
-
 fn unwrap_response<R: CandidType + DeserializeOwned>(
     response: Result<Vec<u8>, RejectResponse>,
 ) -> R {
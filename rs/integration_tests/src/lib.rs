@@ -2,12 +2,20 @@
 use crate::rng::{random, random_bytes, random_principal, random_string};
 use crate::setup::setup_new_env;
 use candid::Principal;
-use event_store_canister::{EventsArgs, InitArgs, PushEventsArgs};
+use event_store_canister::{
+    BatchTooLarge, CardinalityLimit, DappRadarConfig, EventLimitsConfig, EventNameMatcher,
+    EventViolation, EventsArgs, EventsHttpApiConfig, InitArgs, ProducerLimits,
+    ProducerLimitsConfig, PushEventsArgs, PushEventsResponse, SetSampleRateArgs, SetStreamArgs,
+    SetStreamResponse, SetTimeGranularityOverrideArgs, SizeLimit, StreamConfig, StreamRetention,
+    TimestampClamp, TimestampPolicy, UpgradeArgs,
+};
 use event_store_types::{Anonymizable, IdempotentEvent, Milliseconds};
-use pocket_ic::PocketIc;
+use ic_http_certification::{CERTIFICATE_EXPRESSION_HEADER_NAME, DefaultCelBuilder, HttpRequest};
+use pocket_ic::{PocketIc, RejectCode};
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
+use std::time::Duration;
 use test_case::test_case;
 
 mod client;
@@ -47,6 +55,7 @@ fn push_then_read_events_succeeds() {
                     payload: random_bytes(),
                 })
                 .collect(),
+            stream: None,
         },
     );
 
@@ -57,6 +66,7 @@ fn push_then_read_events_succeeds() {
         &EventsArgs {
             start: 0,
             length: 5,
+            stream: None,
         },
     );
 
@@ -81,6 +91,12 @@ fn users_and_source_can_be_anonymized(users: bool, sources: bool) {
         push_events_whitelist: vec![random_principal()],
         read_events_whitelist: vec![random_principal()],
         time_granularity: None,
+        dapp_radar_config: None,
+        events_http_api_config: None,
+        metrics_http_api_config: None,
+        producer_limits: None,
+        event_limits: None,
+        timestamp_policy: None,
     }));
 
     let user = random_string();
@@ -99,6 +115,7 @@ fn users_and_source_can_be_anonymized(users: bool, sources: bool) {
                 source: Some(Anonymizable::new(source.clone(), sources)),
                 payload: Vec::new(),
             }],
+            stream: None,
         },
     );
 
@@ -109,6 +126,7 @@ fn users_and_source_can_be_anonymized(users: bool, sources: bool) {
         &EventsArgs {
             start: 0,
             length: 1,
+            stream: None,
         },
     )
     .events
@@ -145,6 +163,12 @@ fn time_granularity_applied_correctly(time_granularity: Option<Milliseconds>) {
         push_events_whitelist: vec![random_principal()],
         read_events_whitelist: vec![random_principal()],
         time_granularity,
+        dapp_radar_config: None,
+        events_http_api_config: None,
+        metrics_http_api_config: None,
+        producer_limits: None,
+        event_limits: None,
+        timestamp_policy: None,
     }));
 
     client::push_events(
@@ -178,6 +202,7 @@ fn time_granularity_applied_correctly(time_granularity: Option<Milliseconds>) {
                     payload: Vec::new(),
                 },
             ],
+            stream: None,
         },
     );
 
@@ -188,6 +213,7 @@ fn time_granularity_applied_correctly(time_granularity: Option<Milliseconds>) {
         &EventsArgs {
             start: 0,
             length: 3,
+            stream: None,
         },
     )
     .events;
@@ -212,6 +238,1191 @@ fn time_granularity_applied_correctly(time_granularity: Option<Milliseconds>) {
     }
 }
 
+#[test_case(true)]
+#[test_case(false)]
+fn read_events_via_http(public: bool) {
+    let token = random_string();
+    let TestEnv {
+        mut env,
+        canister_id,
+        push_principals,
+        ..
+    } = install_canister(Some(InitArgs {
+        push_events_whitelist: vec![random_principal()],
+        read_events_whitelist: vec![random_principal()],
+        time_granularity: None,
+        dapp_radar_config: None,
+        events_http_api_config: Some(EventsHttpApiConfig {
+            public,
+            tokens: vec![token.clone()],
+        }),
+        metrics_http_api_config: None,
+        producer_limits: None,
+        event_limits: None,
+        timestamp_policy: None,
+    }));
+
+    let payloads = [br#"{"amount":5}"#.to_vec(), vec![0xff, 0x00], Vec::new()];
+
+    client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: payloads
+                .iter()
+                .enumerate()
+                .map(|(i, payload)| IdempotentEvent {
+                    idempotency_key: random(),
+                    name: if i == 1 { "other" } else { "swap" }.to_string(),
+                    timestamp: i as u64,
+                    user: None,
+                    source: None,
+                    payload: payload.clone(),
+                })
+                .collect(),
+            stream: None,
+        },
+    );
+
+    let unauthenticated = client::http_request(
+        &env,
+        canister_id,
+        &HttpRequest::get("/events?start=0&length=10").build(),
+    );
+    assert_eq!(
+        unauthenticated.status_code(),
+        if public { 200 } else { 401 }
+    );
+
+    let response = client::http_request(
+        &env,
+        canister_id,
+        &HttpRequest::get("/events?start=0&length=10")
+            .with_headers(vec![(
+                "Authorization".to_string(),
+                format!("Bearer {token}"),
+            )])
+            .build(),
+    );
+    assert_eq!(response.status_code(), 200);
+    let json: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(json["latest_event_index"], 2);
+    assert_eq!(json["events"][0]["payload"]["amount"], 5);
+    assert_eq!(json["events"][1]["payload_base64"], "/wA=");
+    assert!(json["events"][2].get("payload").is_none());
+
+    let token_in_query = client::http_request(
+        &env,
+        canister_id,
+        &HttpRequest::get(format!("/events?start=0&length=10&token={token}")).build(),
+    );
+    assert_eq!(token_in_query.status_code(), if public { 200 } else { 401 });
+
+    let response = client::http_request(
+        &env,
+        canister_id,
+        &HttpRequest::get("/events?start=0&length=10&name=swap&format=ndjson")
+            .with_headers(vec![(
+                "Authorization".to_string(),
+                format!("Bearer {token}"),
+            )])
+            .build(),
+    );
+    assert_eq!(response.status_code(), 200);
+    let lines: Vec<serde_json::Value> = response
+        .body()
+        .split(|b| *b == b'\n')
+        .filter(|l| !l.is_empty())
+        .map(|l| serde_json::from_slice(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["index"], 2);
+}
+
+#[test]
+fn metrics_endpoint_returns_prometheus_text() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        push_principals,
+        ..
+    } = install_canister(Some(InitArgs {
+        push_events_whitelist: vec![random_principal()],
+        read_events_whitelist: vec![random_principal()],
+        time_granularity: None,
+        dapp_radar_config: None,
+        events_http_api_config: None,
+        metrics_http_api_config: Some(EventsHttpApiConfig {
+            public: true,
+            tokens: Vec::new(),
+        }),
+        producer_limits: None,
+        event_limits: None,
+        timestamp_policy: None,
+    }));
+
+    client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: ["a", "a", "b"]
+                .into_iter()
+                .map(|name| IdempotentEvent {
+                    idempotency_key: random(),
+                    name: name.to_string(),
+                    timestamp: 1000,
+                    user: None,
+                    source: None,
+                    payload: Vec::new(),
+                })
+                .collect(),
+            stream: None,
+        },
+    );
+
+    // Access to the metrics is configured separately from access to the events
+    let events_response =
+        client::http_request(&env, canister_id, &HttpRequest::get("/events").build());
+    assert_eq!(events_response.status_code(), 401);
+
+    let response = client::http_request(&env, canister_id, &HttpRequest::get("/metrics").build());
+    assert_eq!(response.status_code(), 200);
+
+    let text = String::from_utf8(response.body().to_vec()).unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert!(lines.contains(&"event_store_events_total 3"));
+    assert!(lines.contains(&"event_store_dedup_keys 3"));
+    assert!(lines.contains(&"event_store_events_by_name_total{name=\"a\"} 2"));
+    assert!(lines.contains(&"event_store_events_by_name_total{name=\"b\"} 1"));
+}
+
+#[test]
+fn stats_reflect_pushed_events() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        push_principals,
+        ..
+    } = install_canister(None);
+
+    let producer = *push_principals.first().unwrap();
+    let users = [random_string(), random_string()];
+
+    client::push_events(
+        &mut env,
+        producer,
+        canister_id,
+        &PushEventsArgs {
+            events: (0..4)
+                .map(|i| IdempotentEvent {
+                    idempotency_key: random(),
+                    name: if i == 0 { "a" } else { "b" }.to_string(),
+                    timestamp: 1000,
+                    user: Some(Anonymizable::Public(users[i % 2].clone())),
+                    source: Some(Anonymizable::Public("web".to_string())),
+                    payload: Vec::new(),
+                })
+                .collect(),
+            stream: None,
+        },
+    );
+
+    let stats = client::stats(&env, canister_id);
+    assert_eq!(stats.total_events, 4);
+    assert_eq!(stats.latest_event_index, Some(3));
+    assert_eq!(stats.distinct_names, 2);
+    assert_eq!(stats.distinct_users, 2);
+    assert_eq!(stats.distinct_sources, 1);
+    assert_eq!(stats.distinct_values_backfilled_up_to, 4);
+    assert_eq!(stats.dedup_keys, 4);
+    assert!(stats.salt_initialized);
+    assert!(
+        stats
+            .memory
+            .iter()
+            .any(|m| m.name == "events_data" && m.bytes > 0)
+    );
+    assert_eq!(
+        stats
+            .events_per_name
+            .iter()
+            .find(|n| n.name == "b")
+            .map(|n| n.count),
+        Some(3)
+    );
+    assert_eq!(stats.producers.len(), 1);
+    assert_eq!(stats.producers[0].principal, producer);
+    assert!(stats.producers[0].last_push > 0);
+}
+
+#[test]
+fn push_events_rejected_when_producer_limits_exceeded() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        push_principals,
+        ..
+    } = install_canister(Some(InitArgs {
+        push_events_whitelist: vec![random_principal()],
+        read_events_whitelist: vec![random_principal()],
+        time_granularity: None,
+        dapp_radar_config: None,
+        events_http_api_config: None,
+        metrics_http_api_config: None,
+        producer_limits: Some(ProducerLimitsConfig {
+            default: ProducerLimits {
+                max_batch_size: Some(5),
+                max_events_per_minute: Some(8),
+            },
+            per_principal: Vec::new(),
+        }),
+        event_limits: None,
+        timestamp_policy: None,
+    }));
+
+    let producer = *push_principals.first().unwrap();
+    let args = |count: usize| PushEventsArgs {
+        events: (0..count)
+            .map(|_| IdempotentEvent {
+                idempotency_key: random(),
+                name: random_string(),
+                timestamp: 1000,
+                user: None,
+                source: None,
+                payload: Vec::new(),
+            })
+            .collect(),
+        stream: None,
+    };
+
+    assert!(matches!(
+        client::push_events(&mut env, producer, canister_id, &args(6)),
+        PushEventsResponse::BatchTooLarge(BatchTooLarge { max_batch_size: 5 })
+    ));
+    assert!(matches!(
+        client::push_events(&mut env, producer, canister_id, &args(5)),
+        PushEventsResponse::Success
+    ));
+    let PushEventsResponse::RateLimitExceeded(rate_limit_exceeded) =
+        client::push_events(&mut env, producer, canister_id, &args(5))
+    else {
+        panic!();
+    };
+    assert!(rate_limit_exceeded.retry_after <= 60_000);
+
+    env.advance_time(Duration::from_millis(rate_limit_exceeded.retry_after));
+
+    assert!(matches!(
+        client::push_events(&mut env, producer, canister_id, &args(5)),
+        PushEventsResponse::Success
+    ));
+
+    let stats = client::stats(&env, canister_id);
+    assert_eq!(stats.total_events, 10);
+    assert_eq!(stats.producers[0].events, 10);
+    assert_eq!(stats.producers[0].rejected_batches, 2);
+    assert!(stats.producers[0].bytes > 0);
+}
+
+#[test]
+fn events_exceeding_size_limits_rejected_individually() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        push_principals,
+        read_principals,
+        ..
+    } = install_canister(Some(InitArgs {
+        push_events_whitelist: vec![random_principal()],
+        read_events_whitelist: vec![random_principal()],
+        time_granularity: None,
+        dapp_radar_config: None,
+        events_http_api_config: None,
+        metrics_http_api_config: None,
+        producer_limits: None,
+        event_limits: Some(EventLimitsConfig {
+            max_payload_bytes: Some(10),
+            max_name_bytes: Some(5),
+            max_user_bytes: None,
+            max_source_bytes: None,
+            max_distinct_names: None,
+        }),
+        timestamp_policy: None,
+    }));
+
+    let events: Vec<_> = [("ok", 10), ("too_long", 10), ("ok", 11), ("ok", 0)]
+        .into_iter()
+        .map(|(name, payload_len)| IdempotentEvent {
+            idempotency_key: random(),
+            name: name.to_string(),
+            timestamp: 1000,
+            user: None,
+            source: None,
+            payload: vec![0; payload_len],
+        })
+        .collect();
+
+    let PushEventsResponse::PartialSuccess(result) = client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: events.clone(),
+            stream: None,
+        },
+    ) else {
+        panic!();
+    };
+
+    assert_eq!(result.rejected_events.len(), 2);
+    assert_eq!(result.rejected_events[0].index, 1);
+    assert_eq!(
+        result.rejected_events[0].idempotency_key,
+        events[1].idempotency_key
+    );
+    assert_eq!(
+        result.rejected_events[0].violations,
+        vec![EventViolation::NameTooLong(SizeLimit { size: 8, max: 5 })]
+    );
+    assert_eq!(result.rejected_events[1].index, 2);
+    assert_eq!(
+        result.rejected_events[1].violations,
+        vec![EventViolation::PayloadTooLarge(SizeLimit {
+            size: 11,
+            max: 10
+        })]
+    );
+
+    let read_response = client::events(
+        &env,
+        *read_principals.first().unwrap(),
+        canister_id,
+        &EventsArgs {
+            start: 0,
+            length: 10,
+            stream: None,
+        },
+    );
+    assert_eq!(read_response.events.len(), 2);
+}
+
+#[test]
+fn events_with_new_names_rejected_once_distinct_names_limit_reached() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        controller,
+        push_principals,
+        ..
+    } = install_canister(Some(InitArgs {
+        push_events_whitelist: vec![random_principal()],
+        read_events_whitelist: vec![random_principal()],
+        time_granularity: None,
+        dapp_radar_config: None,
+        events_http_api_config: None,
+        metrics_http_api_config: None,
+        producer_limits: None,
+        event_limits: Some(EventLimitsConfig {
+            max_distinct_names: Some(2),
+            ..Default::default()
+        }),
+        timestamp_policy: None,
+    }));
+
+    let PushEventsResponse::PartialSuccess(result) = client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: ["a", "b", "c", "a"]
+                .into_iter()
+                .map(|name| IdempotentEvent {
+                    idempotency_key: random(),
+                    name: name.to_string(),
+                    timestamp: 1000,
+                    user: None,
+                    source: None,
+                    payload: Vec::new(),
+                })
+                .collect(),
+            stream: None,
+        },
+    ) else {
+        panic!();
+    };
+
+    assert_eq!(result.rejected_events.len(), 1);
+    assert_eq!(result.rejected_events[0].index, 2);
+    assert_eq!(
+        result.rejected_events[0].violations,
+        vec![EventViolation::TooManyDistinctNames(CardinalityLimit {
+            max: 2
+        })]
+    );
+
+    let stats = client::stats(&env, canister_id);
+    assert_eq!(stats.total_events, 3);
+    assert_eq!(stats.distinct_names, 2);
+
+    // Named streams don't track distinct names, so the limit doesn't apply to them
+    let response = client::set_stream(
+        &mut env,
+        controller,
+        canister_id,
+        &SetStreamArgs {
+            name: "ops".to_string(),
+            config: StreamConfig {
+                push_events_whitelist: push_principals.clone(),
+                read_events_whitelist: Vec::new(),
+                retention: StreamRetention {
+                    max_age: None,
+                    max_events: None,
+                },
+            },
+        },
+    );
+    assert_eq!(response, SetStreamResponse::Success);
+
+    let response = client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: ["c", "d"]
+                .into_iter()
+                .map(|name| IdempotentEvent {
+                    idempotency_key: random(),
+                    name: name.to_string(),
+                    timestamp: 1000,
+                    user: None,
+                    source: None,
+                    payload: Vec::new(),
+                })
+                .collect(),
+            stream: Some("ops".to_string()),
+        },
+    );
+    assert!(matches!(response, PushEventsResponse::Success));
+    assert_eq!(client::stats(&env, canister_id).distinct_names, 2);
+}
+
+#[test]
+fn timestamps_clamped_and_ingestion_time_recorded() {
+    let hour = 60 * 60 * 1000;
+    let TestEnv {
+        mut env,
+        canister_id,
+        push_principals,
+        read_principals,
+        ..
+    } = install_canister(Some(InitArgs {
+        push_events_whitelist: vec![random_principal()],
+        read_events_whitelist: vec![random_principal()],
+        time_granularity: None,
+        dapp_radar_config: None,
+        events_http_api_config: None,
+        metrics_http_api_config: None,
+        producer_limits: None,
+        event_limits: None,
+        timestamp_policy: Some(TimestampPolicy::Clamp(TimestampClamp {
+            max_past: Some(24 * hour),
+            max_future: Some(hour),
+        })),
+    }));
+
+    let now = env.get_time().as_nanos_since_unix_epoch() / 1_000_000;
+
+    client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: [now, now + 2 * hour, 1000]
+                .into_iter()
+                .map(|timestamp| IdempotentEvent {
+                    idempotency_key: random(),
+                    name: random_string(),
+                    timestamp,
+                    user: None,
+                    source: None,
+                    payload: Vec::new(),
+                })
+                .collect(),
+            stream: None,
+        },
+    );
+
+    let events = client::events(
+        &env,
+        *read_principals.first().unwrap(),
+        canister_id,
+        &EventsArgs {
+            start: 0,
+            length: 3,
+            stream: None,
+        },
+    )
+    .events;
+
+    let ingested_at = events[0].ingested_at.unwrap();
+    assert!(ingested_at >= now);
+    assert!(events.iter().all(|e| e.ingested_at == Some(ingested_at)));
+    assert_eq!(events[0].timestamp, now);
+    assert_eq!(events[1].timestamp, ingested_at + hour);
+    assert_eq!(events[2].timestamp, ingested_at - 24 * hour);
+}
+
+#[test]
+fn time_granularity_overridden_per_event_name() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        controller,
+        push_principals,
+        read_principals,
+    } = install_canister(Some(InitArgs {
+        push_events_whitelist: vec![random_principal()],
+        read_events_whitelist: vec![random_principal()],
+        time_granularity: Some(10),
+        dapp_radar_config: None,
+        events_http_api_config: None,
+        metrics_http_api_config: None,
+        producer_limits: None,
+        event_limits: None,
+        timestamp_policy: None,
+    }));
+
+    for (event_name, time_granularity) in [
+        (EventNameMatcher::Prefix("private_".to_string()), Some(1000)),
+        (EventNameMatcher::Prefix("private_ops_".to_string()), None),
+        (
+            EventNameMatcher::Exact("private_ops_login".to_string()),
+            Some(100),
+        ),
+        // A granularity of zero is treated as no granularity
+        (EventNameMatcher::Exact("zero".to_string()), Some(0)),
+    ] {
+        client::set_time_granularity_override(
+            &mut env,
+            controller,
+            canister_id,
+            &SetTimeGranularityOverrideArgs {
+                event_name,
+                time_granularity,
+            },
+        );
+    }
+
+    let names = [
+        "other",
+        "private_a",
+        "private_ops_b",
+        "private_ops_login",
+        "zero",
+    ];
+
+    client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: names
+                .into_iter()
+                .map(|name| IdempotentEvent {
+                    idempotency_key: random(),
+                    name: name.to_string(),
+                    timestamp: 123_456,
+                    user: None,
+                    source: None,
+                    payload: Vec::new(),
+                })
+                .collect(),
+            stream: None,
+        },
+    );
+
+    let timestamps: Vec<_> = client::events(
+        &env,
+        *read_principals.first().unwrap(),
+        canister_id,
+        &EventsArgs {
+            start: 0,
+            length: 5,
+            stream: None,
+        },
+    )
+    .events
+    .into_iter()
+    .map(|e| e.timestamp)
+    .collect();
+
+    assert_eq!(
+        timestamps,
+        vec![123_450, 123_000, 123_456, 123_400, 123_456]
+    );
+}
+
+#[test]
+fn events_sampled_per_event_name() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        controller,
+        push_principals,
+        read_principals,
+    } = install_canister(None);
+
+    client::set_sample_rate(
+        &mut env,
+        controller,
+        canister_id,
+        &SetSampleRateArgs {
+            event_name: EventNameMatcher::Exact("sampled".to_string()),
+            sample_rate_per_million: 250_000,
+        },
+    );
+
+    client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: (0..400)
+                .map(|i| IdempotentEvent {
+                    idempotency_key: random(),
+                    name: if i % 2 == 0 { "sampled" } else { "unsampled" }.to_string(),
+                    timestamp: 1000,
+                    user: None,
+                    source: None,
+                    payload: Vec::new(),
+                })
+                .collect(),
+            stream: None,
+        },
+    );
+
+    let events = client::events(
+        &env,
+        *read_principals.first().unwrap(),
+        canister_id,
+        &EventsArgs {
+            start: 0,
+            length: 400,
+            stream: None,
+        },
+    )
+    .events;
+
+    let (sampled, unsampled): (Vec<_>, Vec<_>) =
+        events.into_iter().partition(|e| e.name == "sampled");
+
+    assert_eq!(unsampled.len(), 200);
+    assert!(
+        unsampled
+            .iter()
+            .all(|e| e.sample_rate_per_million.is_none())
+    );
+    assert!((20..80).contains(&sampled.len()));
+    assert!(
+        sampled
+            .iter()
+            .all(|e| e.sample_rate_per_million == Some(250_000))
+    );
+}
+
+#[test]
+fn streams_have_own_indexes_whitelists_and_retention() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        controller,
+        push_principals,
+        read_principals,
+    } = install_canister(None);
+
+    let stream_pusher = random_principal();
+    let stream_reader = random_principal();
+
+    let response = client::set_stream(
+        &mut env,
+        controller,
+        canister_id,
+        &SetStreamArgs {
+            name: "ops".to_string(),
+            config: StreamConfig {
+                push_events_whitelist: vec![stream_pusher],
+                read_events_whitelist: vec![stream_reader],
+                retention: StreamRetention {
+                    max_age: None,
+                    max_events: Some(3),
+                },
+            },
+        },
+    );
+    assert_eq!(response, SetStreamResponse::Success);
+
+    let events = |count: u64| {
+        (0..count)
+            .map(|i| IdempotentEvent {
+                idempotency_key: random(),
+                name: random_string(),
+                timestamp: i,
+                user: None,
+                source: None,
+                payload: Vec::new(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let ops_events = events(5);
+    client::push_events(
+        &mut env,
+        stream_pusher,
+        canister_id,
+        &PushEventsArgs {
+            events: ops_events.clone(),
+            stream: Some("ops".to_string()),
+        },
+    );
+    // Idempotency keys are deduplicated per stream, so reusing keys from another stream is fine
+    client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: ops_events[3..].to_vec(),
+            stream: None,
+        },
+    );
+
+    // Principals whitelisted for the default stream can't push to other streams. The call is
+    // rejected rather than trapping, so producers don't mistake it for a problem with the events.
+    for (sender, stream) in [
+        (*push_principals.first().unwrap(), Some("ops".to_string())),
+        (stream_pusher, None),
+        (stream_pusher, Some("unknown".to_string())),
+    ] {
+        let reject = env
+            .update_call(
+                canister_id,
+                sender,
+                "push_events",
+                candid::encode_one(PushEventsArgs {
+                    events: events(1),
+                    stream,
+                })
+                .unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(reject.reject_code, RejectCode::CanisterReject);
+    }
+
+    let ops_response = client::events(
+        &env,
+        stream_reader,
+        canister_id,
+        &EventsArgs {
+            start: 0,
+            length: 10,
+            stream: Some("ops".to_string()),
+        },
+    );
+    assert_eq!(
+        ops_response
+            .events
+            .iter()
+            .map(|e| e.index)
+            .collect::<Vec<_>>(),
+        vec![2, 3, 4]
+    );
+    assert_eq!(ops_response.latest_event_index, Some(4));
+
+    let default_response = client::events(
+        &env,
+        *read_principals.first().unwrap(),
+        canister_id,
+        &EventsArgs {
+            start: 0,
+            length: 10,
+            stream: None,
+        },
+    );
+    assert_eq!(default_response.events.len(), 2);
+    assert_eq!(default_response.latest_event_index, Some(1));
+
+    for (sender, stream) in [
+        (*read_principals.first().unwrap(), Some("ops".to_string())),
+        (stream_reader, None),
+        (stream_reader, Some("unknown".to_string())),
+    ] {
+        let reject = env
+            .query_call(
+                canister_id,
+                sender,
+                "events",
+                candid::encode_one(EventsArgs {
+                    start: 0,
+                    length: 10,
+                    stream,
+                })
+                .unwrap(),
+            )
+            .unwrap_err();
+        assert_eq!(reject.reject_code, RejectCode::CanisterReject);
+    }
+}
+
+#[test]
+fn dapp_radar_range_and_summary_endpoints() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        push_principals,
+        ..
+    } = install_canister(None);
+
+    let day1 = 1714521600000; // 2024-05-01
+    let day2 = day1 + 24 * 60 * 60 * 1000;
+    let users: Vec<_> = (0..3).map(|_| random_string()).collect();
+
+    client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: [
+                (day1, 0),
+                (day1, 0),
+                (day1, 1),
+                (day2 + 1000, 1),
+                (day2 + 2000, 2),
+            ]
+            .into_iter()
+            .map(|(timestamp, user)| IdempotentEvent {
+                idempotency_key: random(),
+                name: random_string(),
+                timestamp,
+                user: Some(Anonymizable::Public(users[user].clone())),
+                source: None,
+                payload: Vec::new(),
+            })
+            .collect(),
+            stream: None,
+        },
+    );
+
+    let summary = http_get_json(
+        &env,
+        canister_id,
+        "/dapp-radar/summary/2024-05-01/2024-05-02",
+    );
+    assert_eq!(summary["totalTransactions"], 5);
+    assert_eq!(summary["uniqueWallets"], 3);
+    assert_eq!(summary["results"][0]["date"], "2024-05-01");
+    assert_eq!(summary["results"][0]["transactions"], 3);
+    assert_eq!(summary["results"][0]["uniqueWallets"], 2);
+    assert_eq!(summary["results"][1]["transactions"], 2);
+
+    let daily = http_get_json(
+        &env,
+        canister_id,
+        "/dapp-radar/aggregated-data/2024-05-01/2024-05-02/daily?page=1",
+    );
+    assert_eq!(daily["pageCount"], 1);
+    assert_eq!(daily["results"].as_array().unwrap().len(), 4);
+    assert_eq!(daily["results"][3]["dateTime"], "2024-05-02");
+
+    let single_day = http_get_json(
+        &env,
+        canister_id,
+        "/dapp-radar/aggregated-data/2024-05-02/daily?page=1",
+    );
+    assert_eq!(single_day["results"].as_array().unwrap().len(), 2);
+    assert!(single_day["results"][0].get("dateTime").is_none());
+
+    let invalid_page = client::http_request(
+        &env,
+        canister_id,
+        &HttpRequest::get("/dapp-radar/aggregated-data/2024-05-02/daily?page=abc").build(),
+    );
+    assert_eq!(invalid_page.status_code(), 400);
+}
+
+#[test]
+fn dapp_radar_transactions_filtered_by_config_and_rebuilt_on_change() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        controller,
+        push_principals,
+        ..
+    } = install_canister(Some(InitArgs {
+        push_events_whitelist: vec![random_principal()],
+        read_events_whitelist: vec![random_principal()],
+        time_granularity: None,
+        dapp_radar_config: Some(DappRadarConfig {
+            transaction_event_names: vec!["swap".to_string()],
+            transaction_event_name_prefixes: vec!["tx_".to_string()],
+        }),
+        events_http_api_config: None,
+        metrics_http_api_config: None,
+        producer_limits: None,
+        event_limits: None,
+        timestamp_policy: None,
+    }));
+
+    client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: ["swap", "tx_send", "telemetry", "swap_quote"]
+                .into_iter()
+                .map(|name| IdempotentEvent {
+                    idempotency_key: random(),
+                    name: name.to_string(),
+                    timestamp: 1714521600000, // 2024-05-01
+                    user: Some(Anonymizable::Public(random_string())),
+                    source: None,
+                    payload: Vec::new(),
+                })
+                .collect(),
+            stream: None,
+        },
+    );
+
+    let summary = http_get_json(&env, canister_id, "/dapp-radar/summary/2024-05-01");
+    assert_eq!(summary["totalTransactions"], 2);
+
+    env.upgrade_canister(
+        canister_id,
+        canister_wasm(),
+        candid::encode_one(Some(UpgradeArgs {
+            dapp_radar_config: Some(DappRadarConfig::default()),
+            events_http_api_config: None,
+            metrics_http_api_config: None,
+            producer_limits: None,
+            event_limits: None,
+            timestamp_policy: None,
+        }))
+        .unwrap(),
+        Some(controller),
+    )
+    .unwrap();
+
+    // Tick to run the job which rebuilds the DappRadar data
+    env.tick();
+    env.tick();
+
+    let summary = http_get_json(&env, canister_id, "/dapp-radar/summary/2024-05-01");
+    assert_eq!(summary["totalTransactions"], 4);
+}
+
+#[test]
+fn integrations_status_reflects_backfill_progress() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        controller,
+        push_principals,
+        ..
+    } = install_canister(Some(InitArgs {
+        push_events_whitelist: vec![random_principal()],
+        read_events_whitelist: vec![random_principal()],
+        time_granularity: None,
+        dapp_radar_config: Some(DappRadarConfig::default()),
+        events_http_api_config: None,
+        metrics_http_api_config: None,
+        producer_limits: None,
+        event_limits: None,
+        timestamp_policy: None,
+    }));
+
+    client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: (0..250)
+                .map(|_| IdempotentEvent {
+                    idempotency_key: random(),
+                    name: "swap".to_string(),
+                    timestamp: 1714521600000, // 2024-05-01
+                    user: Some(Anonymizable::Public(random_string())),
+                    source: None,
+                    payload: Vec::new(),
+                })
+                .collect(),
+            stream: None,
+        },
+    );
+
+    // Events pushed after the integration was configured are processed straight away
+    let status = client::integrations_status(&env, canister_id);
+    assert_eq!(status.latest_event_index, Some(249));
+    let dapp_radar = status
+        .integrations
+        .iter()
+        .find(|i| i.name == "dapp_radar")
+        .unwrap();
+    assert_eq!(dapp_radar.next_event_index, 250);
+    assert_eq!(dapp_radar.events_remaining, 0);
+
+    // Changing the config causes the integration's data to be rebuilt from the first event
+    env.upgrade_canister(
+        canister_id,
+        canister_wasm(),
+        candid::encode_one(Some(UpgradeArgs {
+            dapp_radar_config: Some(DappRadarConfig {
+                transaction_event_names: vec!["swap".to_string()],
+                transaction_event_name_prefixes: Vec::new(),
+            }),
+            events_http_api_config: None,
+            metrics_http_api_config: None,
+            producer_limits: None,
+            event_limits: None,
+            timestamp_policy: None,
+        }))
+        .unwrap(),
+        Some(controller),
+    )
+    .unwrap();
+
+    let status = client::integrations_status(&env, canister_id);
+    let dapp_radar = status
+        .integrations
+        .iter()
+        .find(|i| i.name == "dapp_radar")
+        .unwrap();
+    assert_eq!(dapp_radar.next_event_index, 0);
+    assert_eq!(dapp_radar.events_remaining, 250);
+
+    // Tick to run the job which backfills the integration
+    env.tick();
+    env.tick();
+
+    let status = client::integrations_status(&env, canister_id);
+    let dapp_radar = status
+        .integrations
+        .iter()
+        .find(|i| i.name == "dapp_radar")
+        .unwrap();
+    assert_eq!(dapp_radar.next_event_index, 250);
+    assert_eq!(dapp_radar.events_remaining, 0);
+}
+
+#[test]
+fn dapp_radar_responses_certified_once_day_is_over() {
+    let TestEnv {
+        mut env,
+        canister_id,
+        push_principals,
+        ..
+    } = install_canister(None);
+
+    client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: (0..3)
+                .map(|_| IdempotentEvent {
+                    idempotency_key: random(),
+                    name: random_string(),
+                    timestamp: 1714521600000, // 2024-05-01
+                    user: Some(Anonymizable::Public(random_string())),
+                    source: None,
+                    payload: Vec::new(),
+                })
+                .collect(),
+            stream: None,
+        },
+    );
+
+    // Advance time so that the periodic job which certifies responses runs
+    env.advance_time(Duration::from_secs(60 * 60));
+    env.tick();
+    env.tick();
+
+    for url in [
+        "/dapp-radar/aggregated-data/2024-05-01/daily",
+        "/dapp-radar/aggregated-data/2024-05-01/daily?page=1",
+        "/dapp-radar/aggregated-data/2024-05-01/hourly",
+        "/dapp-radar/summary/2024-05-01",
+    ] {
+        assert_ne!(
+            certificate_expression(&env, canister_id, url),
+            DefaultCelBuilder::skip_certification().to_string()
+        );
+    }
+
+    // Ranges are never certified
+    assert_eq!(
+        certificate_expression(
+            &env,
+            canister_id,
+            "/dapp-radar/summary/2024-05-01/2024-05-02"
+        ),
+        DefaultCelBuilder::skip_certification().to_string()
+    );
+
+    // Pushing another event for the day removes the certification until the job runs again
+    client::push_events(
+        &mut env,
+        *push_principals.first().unwrap(),
+        canister_id,
+        &PushEventsArgs {
+            events: vec![IdempotentEvent {
+                idempotency_key: random(),
+                name: random_string(),
+                timestamp: 1714521600000,
+                user: Some(Anonymizable::Public(random_string())),
+                source: None,
+                payload: Vec::new(),
+            }],
+            stream: None,
+        },
+    );
+    assert_eq!(
+        certificate_expression(&env, canister_id, "/dapp-radar/summary/2024-05-01"),
+        DefaultCelBuilder::skip_certification().to_string()
+    );
+
+    env.advance_time(Duration::from_secs(60 * 60));
+    env.tick();
+    env.tick();
+
+    assert_ne!(
+        certificate_expression(&env, canister_id, "/dapp-radar/summary/2024-05-01"),
+        DefaultCelBuilder::skip_certification().to_string()
+    );
+}
+
+fn certificate_expression(env: &PocketIc, canister_id: Principal, url: &str) -> String {
+    let response = client::http_request(env, canister_id, &HttpRequest::get(url).build());
+    assert_eq!(response.status_code(), 200);
+    assert!(
+        response
+            .headers()
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("ic-certificate"))
+    );
+    response
+        .headers()
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(CERTIFICATE_EXPRESSION_HEADER_NAME))
+        .map(|(_, value)| value.clone())
+        .unwrap()
+}
+
+fn http_get_json(env: &PocketIc, canister_id: Principal, url: &str) -> serde_json::Value {
+    let response = client::http_request(env, canister_id, &HttpRequest::get(url).build());
+    assert_eq!(response.status_code(), 200);
+    serde_json::from_slice(response.body()).unwrap()
+}
+
 fn install_canister(init_args: Option<InitArgs>) -> TestEnv {
     let env = setup_new_env();
     let controller = random_principal();
@@ -220,6 +1431,12 @@ fn install_canister(init_args: Option<InitArgs>) -> TestEnv {
         push_events_whitelist: vec![random_principal()],
         read_events_whitelist: vec![random_principal()],
         time_granularity: None,
+        dapp_radar_config: None,
+        events_http_api_config: None,
+        metrics_http_api_config: None,
+        producer_limits: None,
+        event_limits: None,
+        timestamp_policy: None,
     });
 
     let canister_id = env.create_canister_with_settings(Some(controller), None);
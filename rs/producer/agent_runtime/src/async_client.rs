@@ -0,0 +1,154 @@
+use crate::AgentRuntime;
+use event_store_producer::{
+    Event, EventStoreClient, EventStoreClientBuilder, EventStoreClientInfo, IdempotentEvent,
+    OverflowPolicy, PushError, RetryPolicy, Runtime, ShutdownReport, Spool,
+};
+use ic_agent::Agent;
+use ic_principal::Principal;
+use std::time::Duration;
+
+// An async wrapper around `EventStoreClient`, so it shares the same buffering, overflow policy,
+// spooling, batch splitting, bisection and concurrency. Operations which wait for flushes to
+// complete run on tokio's blocking thread pool so that they don't block the executor. Clones
+// share the same underlying client.
+pub struct AsyncEventStoreClient<R = AgentRuntime> {
+    client: EventStoreClient<R>,
+}
+
+pub struct AsyncEventStoreClientBuilder {
+    builder: EventStoreClientBuilder<AgentRuntime>,
+}
+
+impl AsyncEventStoreClientBuilder {
+    // Must be called from within a tokio runtime
+    pub fn new(event_store_canister_id: Principal, agent: Agent) -> Self {
+        AsyncEventStoreClientBuilder {
+            builder: EventStoreClientBuilder::new(
+                event_store_canister_id,
+                AgentRuntime::new(agent),
+            ),
+        }
+    }
+
+    // Pushes events to the named stream rather than the default stream
+    pub fn with_stream(mut self, stream: impl Into<String>) -> Self {
+        self.builder = self.builder.with_stream(stream);
+        self
+    }
+
+    pub fn with_flush_delay(mut self, duration: Duration) -> Self {
+        self.builder = self.builder.with_flush_delay(duration);
+        self
+    }
+
+    pub fn with_max_batch_size(mut self, max_batch_size: u32) -> Self {
+        self.builder = self.builder.with_max_batch_size(max_batch_size);
+        self
+    }
+
+    pub fn with_max_batch_bytes(mut self, max_batch_bytes: u64) -> Self {
+        self.builder = self.builder.with_max_batch_bytes(max_batch_bytes);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.builder = self.builder.with_retry_policy(retry_policy);
+        self
+    }
+
+    pub fn with_bisect_after_failures(mut self, failures: Option<u32>) -> Self {
+        self.builder = self.builder.with_bisect_after_failures(failures);
+        self
+    }
+
+    pub fn with_max_dead_letters(mut self, max_dead_letters: u32) -> Self {
+        self.builder = self.builder.with_max_dead_letters(max_dead_letters);
+        self
+    }
+
+    pub fn with_max_buffered_events(mut self, max_buffered_events: u32) -> Self {
+        self.builder = self.builder.with_max_buffered_events(max_buffered_events);
+        self
+    }
+
+    pub fn with_max_buffered_bytes(mut self, max_buffered_bytes: u64) -> Self {
+        self.builder = self.builder.with_max_buffered_bytes(max_buffered_bytes);
+        self
+    }
+
+    pub fn with_overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.builder = self.builder.with_overflow_policy(overflow_policy);
+        self
+    }
+
+    pub fn with_event_priority(mut self, event_name: impl Into<String>, priority: u8) -> Self {
+        self.builder = self.builder.with_event_priority(event_name, priority);
+        self
+    }
+
+    pub fn with_max_concurrent_batches(mut self, max_concurrent_batches: u32) -> Self {
+        self.builder = self
+            .builder
+            .with_max_concurrent_batches(max_concurrent_batches);
+        self
+    }
+
+    pub fn with_spool(mut self, spool: impl Spool + Send + 'static) -> Self {
+        self.builder = self.builder.with_spool(spool);
+        self
+    }
+
+    pub fn build(self) -> AsyncEventStoreClient {
+        AsyncEventStoreClient::from(self.builder.build())
+    }
+}
+
+impl<R: Runtime + Send + 'static> AsyncEventStoreClient<R> {
+    // Doesn't block the executor while the client is in use by another thread, see
+    // `EventStoreClient::push_async`
+    pub async fn push(&self, event: Event) -> Result<(), PushError> {
+        self.client.clone().push_async(event).await
+    }
+
+    // Flushes all buffered events, completing once every event has been delivered or abandoned,
+    // or once the timeout expires. Returns false if the timeout expired first.
+    pub async fn flush(&self, timeout: Duration) -> bool {
+        let mut client = self.client.clone();
+        tokio::task::spawn_blocking(move || client.flush_and_wait(timeout))
+            .await
+            .unwrap_or_else(|error| std::panic::resume_unwind(error.into_panic()))
+    }
+
+    // Flushes all buffered events then shuts down the client, returning any events which weren't
+    // delivered before the timeout expired. Once shut down, pushing via any clone of this client
+    // fails with `PushError::ShutDown`.
+    pub async fn shutdown(self, timeout: Duration) -> ShutdownReport {
+        let mut client = self.client;
+        tokio::task::spawn_blocking(move || client.shutdown(timeout))
+            .await
+            .unwrap_or_else(|error| std::panic::resume_unwind(error.into_panic()))
+    }
+
+    // Takes the events which were isolated as causing flushes to fail
+    pub fn take_dead_letters(&self) -> Vec<IdempotentEvent> {
+        self.client.clone().take_dead_letters()
+    }
+
+    pub fn info(&self) -> EventStoreClientInfo {
+        self.client.info()
+    }
+}
+
+impl<R> Clone for AsyncEventStoreClient<R> {
+    fn clone(&self) -> Self {
+        AsyncEventStoreClient {
+            client: self.client.clone(),
+        }
+    }
+}
+
+impl<R> From<EventStoreClient<R>> for AsyncEventStoreClient<R> {
+    fn from(client: EventStoreClient<R>) -> Self {
+        AsyncEventStoreClient { client }
+    }
+}
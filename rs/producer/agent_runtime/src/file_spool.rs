@@ -0,0 +1,142 @@
+use event_store_producer::{IdempotentEvent, Spool};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Once this many events have been acknowledged since the file was last compacted, the file is
+// rewritten to contain only the events which are still pending
+pub const DEFAULT_COMPACTION_THRESHOLD: usize = 10_000;
+
+// A write-ahead spool backed by an append-only file of JSON lines. Each pushed event is appended
+// and synced to disk before it is buffered, so every push pays the cost of an fsync. A checkpoint
+// record is appended once the client no longer holds the event. Checkpoints are not synced, losing
+// one only means the event is flushed again, which the event store ignores since it has already
+// seen the idempotency key.
+pub struct FileSpool {
+    path: PathBuf,
+    file: File,
+    pending: HashSet<u128>,
+    acknowledged_since_compaction: usize,
+    compaction_threshold: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+enum Record {
+    #[serde(rename = "e")]
+    Event(IdempotentEvent),
+    #[serde(rename = "a")]
+    Acknowledged(Vec<u128>),
+}
+
+impl FileSpool {
+    // Creates the file if it doesn't exist. Any events already in it are returned by `recover`.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<FileSpool> {
+        let path = path.as_ref().to_path_buf();
+        let file = open_for_append(&path)?;
+
+        Ok(FileSpool {
+            path,
+            file,
+            pending: HashSet::new(),
+            acknowledged_since_compaction: 0,
+            compaction_threshold: DEFAULT_COMPACTION_THRESHOLD,
+        })
+    }
+
+    pub fn with_compaction_threshold(mut self, compaction_threshold: usize) -> Self {
+        self.compaction_threshold = compaction_threshold;
+        self
+    }
+
+    fn write_record(&mut self, record: &Record) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        self.file.write_all(&line)
+    }
+
+    // Reads the events which were appended but never acknowledged, in the order they were
+    // appended. A partially written final line (eg. if the process crashed mid-append) is skipped.
+    fn read_unacknowledged(&self) -> io::Result<Vec<IdempotentEvent>> {
+        let mut events = Vec::new();
+        let mut acknowledged = HashSet::new();
+
+        for line in BufReader::new(File::open(&self.path)?).lines() {
+            match serde_json::from_str(&line?) {
+                Ok(Record::Event(event)) => events.push(event),
+                Ok(Record::Acknowledged(keys)) => acknowledged.extend(keys),
+                Err(_) => continue,
+            }
+        }
+        events.retain(|e| !acknowledged.contains(&e.idempotency_key));
+        Ok(events)
+    }
+
+    // Atomically replaces the file with one containing only the given events
+    fn compact(&mut self, events: &[IdempotentEvent]) -> io::Result<()> {
+        let temp_path = self.path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        for event in events {
+            serde_json::to_writer(&mut writer, &Record::Event(event.clone()))?;
+            writer.write_all(b"\n")?;
+        }
+        writer.into_inner()?.sync_all()?;
+        fs::rename(&temp_path, &self.path)?;
+
+        self.file = open_for_append(&self.path)?;
+        self.acknowledged_since_compaction = 0;
+        Ok(())
+    }
+}
+
+impl Spool for FileSpool {
+    fn append(&mut self, events: &[IdempotentEvent]) -> io::Result<()> {
+        for event in events {
+            self.write_record(&Record::Event(event.clone()))?;
+        }
+        self.file.sync_data()?;
+        self.pending
+            .extend(events.iter().map(|e| e.idempotency_key));
+        Ok(())
+    }
+
+    fn acknowledge(&mut self, idempotency_keys: &[u128]) -> io::Result<()> {
+        let keys: Vec<_> = idempotency_keys
+            .iter()
+            .copied()
+            .filter(|k| self.pending.remove(k))
+            .collect();
+
+        if keys.is_empty() {
+            return Ok(());
+        }
+        self.acknowledged_since_compaction += keys.len();
+
+        if self.pending.is_empty() {
+            // Nothing is outstanding so the whole file can be discarded
+            self.acknowledged_since_compaction = 0;
+            self.file.set_len(0)
+        } else {
+            self.write_record(&Record::Acknowledged(keys))?;
+            if self.acknowledged_since_compaction >= self.compaction_threshold {
+                let events = self.read_unacknowledged()?;
+                self.compact(&events)?;
+            }
+            Ok(())
+        }
+    }
+
+    // Returns the events which were appended but never acknowledged, in the order they were
+    // appended, then compacts the file so that it only contains those events
+    fn recover(&mut self) -> io::Result<Vec<IdempotentEvent>> {
+        let events = self.read_unacknowledged()?;
+        self.compact(&events)?;
+        self.pending = events.iter().map(|e| e.idempotency_key).collect();
+        Ok(events)
+    }
+}
+
+fn open_for_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
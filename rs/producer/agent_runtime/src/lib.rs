@@ -1,23 +1,34 @@
-use event_store_canister::PushEventsArgs;
-use event_store_producer::{
-    FLUSH_OUTCOME_FAILED_SHOULD_RETRY, FLUSH_OUTCOME_SUCCESS, FlushOutcome, IdempotentEvent,
-    Runtime, TimestampMillis,
-};
-use ic_agent::Agent;
+use event_store_canister::{PushEventsArgs, PushEventsResponse};
+use event_store_producer::{FlushError, FlushOutcome, IdempotentEvent, Runtime, TimestampMillis};
+use ic_agent::{Agent, AgentError};
 use ic_principal::Principal;
 use rand::random;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::runtime::Handle;
 use tokio_util::sync::CancellationToken;
 
+mod async_client;
+mod file_spool;
+#[cfg(test)]
+mod tests;
+
+pub use async_client::{AsyncEventStoreClient, AsyncEventStoreClientBuilder};
+pub use file_spool::{DEFAULT_COMPACTION_THRESHOLD, FileSpool};
+
 pub struct AgentRuntime {
     agent: Agent,
+    // Tasks are spawned via this handle since flushes may complete, and schedule further work, on
+    // threads outside of the tokio runtime
+    tokio_handle: Handle,
     scheduler_task_cancellation_token: Option<CancellationToken>,
 }
 
 impl AgentRuntime {
+    // Must be called from within a tokio runtime
     pub fn new(agent: Agent) -> AgentRuntime {
         AgentRuntime {
             agent,
+            tokio_handle: Handle::current(),
             scheduler_task_cancellation_token: None,
         }
     }
@@ -35,7 +46,7 @@ impl Runtime for AgentRuntime {
         let token = CancellationToken::new();
         self.scheduler_task_cancellation_token = Some(token.clone());
 
-        tokio::spawn(async move {
+        self.tokio_handle.spawn(async move {
             tokio::select! {
                 _ = token.cancelled() => {}
                 _ = tokio::time::sleep(delay) => callback()
@@ -46,13 +57,16 @@ impl Runtime for AgentRuntime {
     fn flush<F: FnOnce(FlushOutcome) + Send + 'static>(
         &mut self,
         canister_id: Principal,
+        stream: Option<String>,
         events: Vec<IdempotentEvent>,
         on_complete: F,
     ) {
         self.cancel_scheduler_task();
         let agent = self.agent.clone();
 
-        tokio::spawn(async move { flush_async(canister_id, agent, events, on_complete).await });
+        self.tokio_handle.spawn(async move {
+            flush_async(canister_id, stream, agent, events, on_complete).await
+        });
     }
 
     fn rng(&mut self) -> u128 {
@@ -69,19 +83,96 @@ impl Runtime for AgentRuntime {
 
 async fn flush_async<F: FnOnce(FlushOutcome) + Send + 'static>(
     canister_id: Principal,
+    stream: Option<String>,
     agent: Agent,
     events: Vec<IdempotentEvent>,
     on_complete: F,
 ) {
-    if agent
+    on_complete(push_events(&agent, canister_id, stream, events).await)
+}
+
+async fn push_events(
+    agent: &Agent,
+    canister_id: Principal,
+    stream: Option<String>,
+    events: Vec<IdempotentEvent>,
+) -> FlushOutcome {
+    match agent
         .update(&canister_id, "push_events".to_string())
-        .with_arg(candid::encode_one(PushEventsArgs { events }).unwrap())
+        .with_arg(candid::encode_one(PushEventsArgs { events, stream }).unwrap())
         .call_and_wait()
         .await
-        .is_err()
     {
-        on_complete(FLUSH_OUTCOME_FAILED_SHOULD_RETRY)
-    } else {
-        on_complete(FLUSH_OUTCOME_SUCCESS)
+        // Events rejected individually would be rejected again if retried
+        Ok(bytes) => match candid::decode_one(&bytes) {
+            Ok(PushEventsResponse::Success | PushEventsResponse::PartialSuccess(_)) => {
+                FlushOutcome::Success
+            }
+            Ok(PushEventsResponse::BatchTooLarge(result)) => {
+                FlushOutcome::FailedBatchTooLarge(FlushError {
+                    reject_code: None,
+                    message: format!(
+                        "Batch too large, max batch size is {}",
+                        result.max_batch_size
+                    ),
+                    retry_after: None,
+                    max_batch_size: Some(result.max_batch_size),
+                })
+            }
+            Ok(PushEventsResponse::RateLimitExceeded(result)) => {
+                FlushOutcome::FailedShouldRetry(FlushError {
+                    reject_code: None,
+                    message: format!("Rate limit exceeded, retry after {}ms", result.retry_after),
+                    retry_after: Some(Duration::from_millis(result.retry_after)),
+                    max_batch_size: None,
+                })
+            }
+            // Older versions of the event store return nothing
+            Err(_) if is_empty_reply(&bytes) => FlushOutcome::Success,
+            // The event store would return the same reply if retried
+            Err(error) => FlushOutcome::FailedShouldntRetry(FlushError {
+                reject_code: None,
+                message: format!("Failed to decode 'push_events' response: {error}"),
+                retry_after: None,
+                max_batch_size: None,
+            }),
+        },
+        Err(error) => agent_error_outcome(error),
     }
 }
+
+fn agent_error_outcome(error: AgentError) -> FlushOutcome {
+    let error_without_reject_code = |error: &AgentError| FlushError {
+        reject_code: None,
+        message: error.to_string(),
+        retry_after: None,
+        max_batch_size: None,
+    };
+
+    match error {
+        AgentError::CertifiedReject { reject, .. }
+        | AgentError::UncertifiedReject { reject, .. } => {
+            FlushOutcome::from_reject(reject.reject_code as u32, reject.reject_message)
+        }
+        // The boundary node rejects requests which exceed the ingress message size limit
+        AgentError::HttpError(ref payload) if payload.status == 413 => {
+            FlushOutcome::FailedBatchTooLarge(error_without_reject_code(&error))
+        }
+        AgentError::HttpError(ref payload) if matches!(payload.status, 401 | 403) => {
+            FlushOutcome::FailedShouldntRetry(error_without_reject_code(&error))
+        }
+        // The agent is misconfigured, so every attempt would fail in the same way
+        AgentError::InvalidReplicaUrl(_)
+        | AgentError::UrlParseError(_)
+        | AgentError::SigningError(_)
+        | AgentError::PrincipalError(_) => {
+            FlushOutcome::FailedShouldntRetry(error_without_reject_code(&error))
+        }
+        // Eg. timeouts, transport errors and other HTTP errors
+        _ => FlushOutcome::FailedShouldRetry(error_without_reject_code(&error)),
+    }
+}
+
+fn is_empty_reply(bytes: &[u8]) -> bool {
+    bytes.is_empty() || candid::de::IDLDeserialize::new(bytes).is_ok_and(|de| de.is_done())
+}
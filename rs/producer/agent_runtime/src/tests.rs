@@ -0,0 +1,327 @@
+use crate::{AsyncEventStoreClient, FileSpool, is_empty_reply};
+use event_store_canister::PushEventsResponse;
+use event_store_producer::{
+    EventBuilder, EventStoreClientBuilder, FlushError, FlushOutcome, IdempotentEvent, PushError,
+    RetryPolicy, Runtime, Spool, TimestampMillis,
+};
+use ic_principal::Principal;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::runtime::Handle;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[tokio::test]
+async fn flush_delivers_all_events_in_batches() {
+    let canister = TestCanister::default();
+    let client = canister.client(Duration::from_secs(300), None);
+
+    for i in 0..25 {
+        client
+            .push(EventBuilder::new(i.to_string(), 0).build())
+            .await
+            .unwrap();
+    }
+    assert!(client.flush(TIMEOUT).await);
+
+    assert_eq!(canister.batch_sizes(), [10, 10, 5]);
+}
+
+#[tokio::test]
+async fn events_flushed_once_flush_delay_reached() {
+    let canister = TestCanister::default();
+    let client = canister.client(Duration::from_millis(50), None);
+
+    client
+        .push(EventBuilder::new("event", 0).build())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(canister.batch_sizes().is_empty());
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(canister.batch_sizes(), [1]);
+}
+
+#[tokio::test]
+async fn flush_waits_for_failed_batches_to_be_retried() {
+    let canister = TestCanister::default();
+    canister.fail_next(2);
+    let client = canister.client(Duration::from_secs(300), None);
+
+    for i in 0..5 {
+        client
+            .push(EventBuilder::new(i.to_string(), 0).build())
+            .await
+            .unwrap();
+    }
+    assert!(client.flush(TIMEOUT).await);
+
+    assert_eq!(canister.batch_sizes(), [5, 5, 5]);
+    assert_eq!(canister.events_delivered(), 5);
+}
+
+#[tokio::test]
+async fn flush_completes_once_retries_exhausted() {
+    let canister = TestCanister::default();
+    canister.fail_next(10);
+    let client = canister.client(Duration::from_secs(300), Some(3));
+
+    client
+        .push(EventBuilder::new("event", 0).build())
+        .await
+        .unwrap();
+    assert!(client.flush(TIMEOUT).await);
+
+    assert_eq!(canister.batch_sizes(), [1, 1, 1]);
+    assert_eq!(canister.events_delivered(), 0);
+}
+
+#[tokio::test]
+async fn shutdown_flushes_then_rejects_pushes() {
+    let canister = TestCanister::default();
+    let client = canister.client(Duration::from_secs(300), None);
+    let clone = client.clone();
+
+    for i in 0..15 {
+        client
+            .push(EventBuilder::new(i.to_string(), 0).build())
+            .await
+            .unwrap();
+    }
+    let report = client.shutdown(TIMEOUT).await;
+
+    assert!(report.undelivered.is_empty());
+    assert_eq!(canister.events_delivered(), 15);
+    assert_eq!(
+        clone.push(EventBuilder::new("event", 0).build()).await,
+        Err(PushError::ShutDown)
+    );
+}
+
+#[tokio::test]
+async fn flush_and_shutdown_time_out_during_outage() {
+    let canister = TestCanister::default();
+    canister.fail_next(1000);
+    let client = canister.client(Duration::from_secs(300), None);
+
+    for i in 0..3 {
+        client
+            .push(EventBuilder::new(i.to_string(), 0).build())
+            .await
+            .unwrap();
+    }
+    assert!(!client.flush(Duration::from_millis(100)).await);
+
+    // The undelivered events are handed back rather than being lost
+    let report = client.shutdown(Duration::from_millis(100)).await;
+    assert_eq!(report.undelivered.len() as u32 + report.events_in_flight, 3);
+    assert_eq!(canister.events_delivered(), 0);
+}
+
+#[tokio::test]
+async fn batches_rejected_as_too_large_are_split() {
+    let canister = TestCanister::default();
+    canister.inner.lock().unwrap().max_batch_size = Some(3);
+    let client = canister.client(Duration::from_secs(300), None);
+
+    for i in 0..10 {
+        client
+            .push(EventBuilder::new(i.to_string(), 0).build())
+            .await
+            .unwrap();
+    }
+    assert!(client.flush(TIMEOUT).await);
+
+    assert_eq!(canister.batch_sizes(), [10, 5, 2, 2, 2, 2, 2]);
+    assert_eq!(canister.events_delivered(), 10);
+}
+
+#[test]
+fn file_spool_recovers_unacknowledged_events() {
+    let path = std::env::temp_dir().join(format!("event_store_spool_{}", rand::random::<u64>()));
+    let events: Vec<_> = (0..5)
+        .map(|i| EventBuilder::new(i.to_string(), 0).build().to_idempotent(i))
+        .collect();
+
+    let mut spool = FileSpool::open(&path).unwrap();
+    spool.append(&events[..3]).unwrap();
+    spool.acknowledge(&[1]).unwrap();
+    spool.append(&events[3..]).unwrap();
+    spool.acknowledge(&[3]).unwrap();
+    drop(spool);
+
+    // Simulate the process crashing part way through an append
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(b"{\"e\":{\"idempo").unwrap();
+    drop(file);
+
+    let mut spool = FileSpool::open(&path).unwrap();
+    let recovered = spool.recover().unwrap();
+    let keys: Vec<_> = recovered.iter().map(|e| e.idempotency_key).collect();
+    assert_eq!(keys, [0, 2, 4]);
+
+    spool.acknowledge(&keys).unwrap();
+    assert!(spool.recover().unwrap().is_empty());
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn file_spool_compacted_once_threshold_reached() {
+    let path = std::env::temp_dir().join(format!("event_store_spool_{}", rand::random::<u64>()));
+    let events: Vec<_> = (0..10)
+        .map(|i| EventBuilder::new(i.to_string(), 0).build().to_idempotent(i))
+        .collect();
+
+    let mut spool = FileSpool::open(&path).unwrap().with_compaction_threshold(4);
+    spool.append(&events).unwrap();
+    let initial_len = std::fs::metadata(&path).unwrap().len();
+
+    // The file only grows until the threshold is reached, then shrinks to the pending events
+    spool.acknowledge(&[0, 1, 2]).unwrap();
+    assert!(std::fs::metadata(&path).unwrap().len() > initial_len);
+    spool.acknowledge(&[3]).unwrap();
+    assert!(std::fs::metadata(&path).unwrap().len() < initial_len);
+
+    let keys: Vec<_> = FileSpool::open(&path)
+        .unwrap()
+        .recover()
+        .unwrap()
+        .iter()
+        .map(|e| e.idempotency_key)
+        .collect();
+    assert_eq!(keys, [4, 5, 6, 7, 8, 9]);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn only_empty_replies_treated_as_success() {
+    assert!(is_empty_reply(&[]));
+    assert!(is_empty_reply(&candid::encode_args(()).unwrap()));
+    assert!(!is_empty_reply(
+        &candid::encode_one(PushEventsResponse::Success).unwrap()
+    ));
+    assert!(!is_empty_reply(b"not candid"));
+}
+
+#[derive(Default, Clone)]
+struct TestCanister {
+    inner: Arc<Mutex<TestCanisterInner>>,
+}
+
+#[derive(Default)]
+struct TestCanisterInner {
+    batches: Vec<(Vec<IdempotentEvent>, FlushOutcome)>,
+    outcomes: VecDeque<FlushOutcome>,
+    max_batch_size: Option<usize>,
+}
+
+impl TestCanister {
+    fn client(
+        &self,
+        flush_delay: Duration,
+        max_attempts: Option<u32>,
+    ) -> AsyncEventStoreClient<TestCanisterRuntime> {
+        let runtime = TestCanisterRuntime {
+            canister: self.clone(),
+            tokio_handle: Handle::current(),
+        };
+
+        EventStoreClientBuilder::new(Principal::anonymous(), runtime)
+            .with_flush_delay(flush_delay)
+            .with_max_batch_size(10)
+            .with_retry_policy(RetryPolicy {
+                initial_delay: Duration::from_millis(10),
+                multiplier: 2.0,
+                max_delay: Duration::from_millis(50),
+                jitter: 0.0,
+                max_attempts,
+            })
+            .build()
+            .into()
+    }
+
+    fn push_events(&self, events: Vec<IdempotentEvent>) -> FlushOutcome {
+        let mut inner = self.inner.lock().unwrap();
+        let outcome = if inner.max_batch_size.is_some_and(|max| events.len() > max) {
+            FlushOutcome::FailedBatchTooLarge(FlushError {
+                reject_code: None,
+                message: "Batch too large".to_string(),
+                retry_after: None,
+                max_batch_size: None,
+            })
+        } else {
+            inner.outcomes.pop_front().unwrap_or_default()
+        };
+        inner.batches.push((events, outcome.clone()));
+        outcome
+    }
+
+    fn fail_next(&self, count: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .outcomes
+            .extend((0..count).map(|_| FlushOutcome::from_reject(2, "Unavailable".to_string())));
+    }
+
+    fn batch_sizes(&self) -> Vec<usize> {
+        let inner = self.inner.lock().unwrap();
+        inner.batches.iter().map(|(b, _)| b.len()).collect()
+    }
+
+    fn events_delivered(&self) -> usize {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .batches
+            .iter()
+            .filter(|(_, outcome)| *outcome == FlushOutcome::Success)
+            .map(|(b, _)| b.len())
+            .sum()
+    }
+}
+
+// Behaves like `AgentRuntime` but pushes events to a `TestCanister`
+struct TestCanisterRuntime {
+    canister: TestCanister,
+    tokio_handle: Handle,
+}
+
+impl Runtime for TestCanisterRuntime {
+    fn schedule_flush<F: FnOnce() + Send + 'static>(&mut self, delay: Duration, callback: F) {
+        self.tokio_handle.spawn(async move {
+            tokio::time::sleep(delay).await;
+            callback();
+        });
+    }
+
+    fn flush<F: FnOnce(FlushOutcome) + Send + 'static>(
+        &mut self,
+        _event_store_canister_id: Principal,
+        _stream: Option<String>,
+        events: Vec<IdempotentEvent>,
+        on_complete: F,
+    ) {
+        let canister = self.canister.clone();
+        self.tokio_handle
+            .spawn(async move { on_complete(canister.push_events(events)) });
+    }
+
+    fn rng(&mut self) -> u128 {
+        rand::random()
+    }
+
+    fn now(&self) -> TimestampMillis {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+}
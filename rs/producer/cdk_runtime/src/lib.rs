@@ -1,9 +1,6 @@
-use event_store_canister::PushEventsArgs;
-use event_store_producer::{
-    FLUSH_OUTCOME_FAILED_SHOULD_RETRY, FLUSH_OUTCOME_SUCCESS, FlushOutcome, IdempotentEvent,
-    Runtime, TimestampMillis,
-};
-use ic_cdk::call::Call;
+use event_store_canister::{PushEventsArgs, PushEventsResponse};
+use event_store_producer::{FlushError, FlushOutcome, IdempotentEvent, Runtime, TimestampMillis};
+use ic_cdk::call::{Call, CallFailed};
 use ic_cdk_timers::TimerId;
 use ic_principal::Principal;
 use rand::rngs::StdRng;
@@ -34,11 +31,12 @@ impl Runtime for CdkRuntime {
     fn flush<F: FnOnce(FlushOutcome) + Send + 'static>(
         &mut self,
         canister_id: Principal,
+        stream: Option<String>,
         events: Vec<IdempotentEvent>,
         on_complete: F,
     ) {
         self.clear_timer();
-        ic_cdk::futures::spawn(flush_async(canister_id, events, on_complete))
+        ic_cdk::futures::spawn(flush_async(canister_id, stream, events, on_complete))
     }
 
     fn rng(&mut self) -> u128 {
@@ -52,22 +50,112 @@ impl Runtime for CdkRuntime {
 
 async fn flush_async<F: FnOnce(FlushOutcome)>(
     canister_id: Principal,
+    stream: Option<String>,
     events: Vec<IdempotentEvent>,
     on_complete: F,
 ) {
     let events_len = events.len();
-    if let Err(error) = Call::unbounded_wait(canister_id, "push_events")
-        .with_arg(PushEventsArgs { events })
+    match Call::unbounded_wait(canister_id, "push_events")
+        .with_arg(PushEventsArgs { events, stream })
         .await
     {
-        on_complete(FLUSH_OUTCOME_FAILED_SHOULD_RETRY);
-        error!(%canister_id, events = events_len, ?error, "Failed to call 'push_events'");
-    } else {
-        on_complete(FLUSH_OUTCOME_SUCCESS);
-        trace!(%canister_id, events = events_len, "Successfully called `push_events`");
+        Ok(response) => match response.candid::<PushEventsResponse>() {
+            Ok(PushEventsResponse::Success) => {
+                on_complete(FlushOutcome::Success);
+                trace!(%canister_id, events = events_len, "Successfully called `push_events`");
+            }
+            // Events rejected individually would be rejected again if retried
+            Ok(PushEventsResponse::PartialSuccess(result)) => {
+                on_complete(FlushOutcome::Success);
+                error!(
+                    %canister_id,
+                    events = events_len,
+                    rejected = ?result.rejected_events,
+                    "Some events rejected by 'push_events'"
+                );
+            }
+            Ok(PushEventsResponse::BatchTooLarge(result)) => {
+                on_complete(FlushOutcome::FailedBatchTooLarge(FlushError {
+                    reject_code: None,
+                    message: format!(
+                        "Batch too large, max batch size is {}",
+                        result.max_batch_size
+                    ),
+                    retry_after: None,
+                    max_batch_size: Some(result.max_batch_size),
+                }));
+                error!(
+                    %canister_id,
+                    events = events_len,
+                    max_batch_size = result.max_batch_size,
+                    "Batch rejected by 'push_events' for being too large"
+                );
+            }
+            Ok(PushEventsResponse::RateLimitExceeded(result)) => {
+                on_complete(FlushOutcome::FailedShouldRetry(FlushError {
+                    reject_code: None,
+                    message: format!("Rate limit exceeded, retry after {}ms", result.retry_after),
+                    retry_after: Some(Duration::from_millis(result.retry_after)),
+                    max_batch_size: None,
+                }));
+                error!(
+                    %canister_id,
+                    events = events_len,
+                    retry_after = result.retry_after,
+                    "Events rejected by 'push_events' due to the rate limit"
+                );
+            }
+            // Older versions of the event store return nothing
+            Err(_) if is_empty_reply(&response) => {
+                on_complete(FlushOutcome::Success);
+                trace!(%canister_id, events = events_len, "Successfully called `push_events`");
+            }
+            // The event store would return the same reply if retried
+            Err(error) => {
+                on_complete(FlushOutcome::FailedShouldntRetry(FlushError {
+                    reject_code: None,
+                    message: format!("Failed to decode 'push_events' response: {error}"),
+                    retry_after: None,
+                    max_batch_size: None,
+                }));
+                error!(
+                    %canister_id,
+                    events = events_len,
+                    ?error,
+                    "Failed to decode 'push_events' response"
+                );
+            }
+        },
+        Err(error) => {
+            on_complete(call_failed_outcome(&error));
+            error!(%canister_id, events = events_len, ?error, "Failed to call 'push_events'");
+        }
     }
 }
 
+fn call_failed_outcome(error: &CallFailed) -> FlushOutcome {
+    match error {
+        CallFailed::CallRejected(rejected) => FlushOutcome::from_reject(
+            rejected.raw_reject_code(),
+            rejected.reject_message().to_string(),
+        ),
+        // The call couldn't be made, either because the canister's queues are full or because its
+        // cycles balance is too low, both of which may resolve themselves
+        CallFailed::CallPerformFailed(_) | CallFailed::InsufficientLiquidCycleBalance(_) => {
+            FlushOutcome::FailedShouldRetry(FlushError {
+                reject_code: None,
+                message: error.to_string(),
+                retry_after: None,
+                max_batch_size: None,
+            })
+        }
+    }
+}
+
+fn is_empty_reply(bytes: &[u8]) -> bool {
+    bytes.is_empty() || candid::de::IDLDeserialize::new(bytes).is_ok_and(|de| de.is_done())
+}
+
 impl Default for CdkRuntime {
     fn default() -> Self {
         CdkRuntime {
@@ -1,6 +1,11 @@
 use ic_principal::Principal;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::task::{Context, Poll, Waker};
 use std::time::Duration;
 use std::{mem, thread};
 
@@ -9,17 +14,101 @@ pub use event_store_types::{Event, EventBuilder, IdempotentEvent, TimestampMilli
 #[cfg(test)]
 mod tests;
 
-const DEFAULT_FLUSH_DELAY: Duration = Duration::from_secs(300);
-const DEFAULT_MAX_BATCH_SIZE: u32 = 1000;
+pub const DEFAULT_MAX_CONCURRENT_BATCHES: u32 = 1;
+pub const DEFAULT_FLUSH_DELAY: Duration = Duration::from_secs(300);
+pub const DEFAULT_MAX_BATCH_SIZE: u32 = 1000;
+// Leaves headroom below the 2MiB limit on ingress messages and inter-canister calls
+pub const DEFAULT_MAX_BATCH_BYTES: u64 = 1_500_000;
+pub const DEFAULT_BISECT_AFTER_FAILURES: u32 = 3;
+pub const DEFAULT_MAX_DEAD_LETTERS: u32 = 1000;
+const JITTER_RESOLUTION: u128 = 1_000_000;
+pub const DEFAULT_EVENT_PRIORITY: u8 = 100;
+// Covers the idempotency key, timestamp and the Candid overhead of each field
+const EVENT_SIZE_OVERHEAD: u64 = 48;
+// The number of consecutive successful flushes after which the batch size limit is doubled
+const BATCH_SIZE_LIMIT_RECOVERY_FLUSHES: u32 = 10;
 
-pub type FlushOutcome = u8;
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FlushOutcome {
+    #[default]
+    Success,
+    // The batch may be accepted if retried, eg. if the event store was temporarily unreachable
+    FailedShouldRetry(FlushError),
+    // The batch would be rejected again if retried, eg. if the caller isn't authorized to push
+    // events, so its events are dropped and counted in `total_events_rejected_by_store`
+    FailedShouldntRetry(FlushError),
+    // The batch was rejected for being too large, so it is split and its events retried in smaller
+    // batches
+    FailedBatchTooLarge(FlushError),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FlushError {
+    // Set if the call to the event store was rejected
+    pub reject_code: Option<u32>,
+    pub message: String,
+    // Set if the event store asked for the batch not to be retried until this delay has elapsed,
+    // in which case the retry delay is at least this long
+    #[serde(default)]
+    pub retry_after: Option<Duration>,
+    // Set if the batch was rejected for containing more events than the event store accepts
+    #[serde(default)]
+    pub max_batch_size: Option<u32>,
+}
+
+impl FlushOutcome {
+    // Classifies a rejected call to the event store by its reject code, as defined in the IC
+    // interface spec
+    pub fn from_reject(reject_code: u32, message: String) -> FlushOutcome {
+        let error = FlushError {
+            reject_code: Some(reject_code),
+            message,
+            retry_after: None,
+            max_batch_size: None,
+        };
+        match reject_code {
+            // SysFatal, DestinationInvalid or CanisterReject (eg. the caller isn't whitelisted)
+            1 | 3 | 4 => FlushOutcome::FailedShouldntRetry(error),
+            // SysTransient, SysUnknown or CanisterError. A canister error may be caused by the
+            // event store being stopped, or by an event in the batch, which bisection will isolate.
+            _ => FlushOutcome::FailedShouldRetry(error),
+        }
+    }
+
+    pub fn error(&self) -> Option<&FlushError> {
+        match self {
+            FlushOutcome::Success => None,
+            FlushOutcome::FailedShouldRetry(error)
+            | FlushOutcome::FailedShouldntRetry(error)
+            | FlushOutcome::FailedBatchTooLarge(error) => Some(error),
+        }
+    }
+}
+
+impl FlushError {
+    // Whether the failure may have been caused by the events in the batch, ie. the event store
+    // trapped while processing them (CanisterError). Other failures, such as rate limiting or the
+    // event store being unreachable, are retried with backoff but never bisect the batch.
+    pub fn may_be_caused_by_events(&self) -> bool {
+        self.reject_code == Some(5)
+    }
+}
+
+impl Display for FlushError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.reject_code {
+            Some(code) => write!(f, "Call rejected ({code}): {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
 
-pub const FLUSH_OUTCOME_SUCCESS: u8 = 0;
-pub const FLUSH_OUTCOME_FAILED_SHOULD_RETRY: u8 = 1;
-pub const FLUSH_OUTCOME_FAILED_SHOULDNT_RETRY: u8 = 2;
+impl std::error::Error for FlushError {}
 
 pub struct EventStoreClient<R> {
     inner: Arc<Mutex<ClientInner<R>>>,
+    // Notified each time a flush completes
+    flush_completed: Arc<Condvar>,
 }
 
 type Client<R> = EventStoreClient<R>;
@@ -28,21 +117,222 @@ type ClientBuilder<R> = EventStoreClientBuilder<R>;
 #[derive(Serialize, Deserialize)]
 struct ClientInner<R> {
     event_store_canister_id: Principal,
+    #[serde(default)]
+    stream: Option<String>,
     runtime: R,
     flush_delay: Duration,
     max_batch_size: usize,
     events: Vec<IdempotentEvent>,
     #[serde(skip)]
     next_flush_scheduled: Option<TimestampMillis>,
+    // True while `batches_in_flight` is non-zero
     flush_in_progress: bool,
     total_events_flushed: u64,
+    #[serde(default)]
+    retry_policy: RetryPolicy,
+    #[serde(default)]
+    consecutive_failures: u32,
+    // The number of those failures which may have been caused by the events being flushed, only
+    // these count towards `bisect_after_failures`
+    #[serde(default)]
+    consecutive_event_failures: u32,
+    #[serde(default)]
+    total_events_abandoned: u64,
+    #[serde(default)]
+    total_events_rejected_by_store: u64,
+    #[serde(default)]
+    max_buffered_events: Option<u32>,
+    #[serde(default)]
+    max_buffered_bytes: Option<u64>,
+    #[serde(default)]
+    overflow_policy: OverflowPolicy,
+    #[serde(default)]
+    event_priorities: BTreeMap<String, u8>,
+    // Recalculated from `events` when deserialized
+    #[serde(skip)]
+    buffered_bytes: u64,
+    #[serde(default)]
+    total_events_dropped: u64,
+    #[serde(default)]
+    total_events_rejected: u64,
+    // Set by `flush_now`, causes batches to be flushed back to back until the buffer is empty
+    #[serde(skip)]
+    flush_all: bool,
+    #[serde(skip)]
+    events_in_flight: usize,
+    #[serde(skip)]
+    shut_down: bool,
+    #[serde(skip)]
+    spool: Option<Box<dyn Spool + Send>>,
+    #[serde(default)]
+    spool_errors: u64,
+    #[serde(default = "default_max_concurrent_batches")]
+    max_concurrent_batches: usize,
+    #[serde(skip)]
+    batches_in_flight: usize,
+    #[serde(default = "default_max_batch_bytes")]
+    max_batch_bytes: u64,
+    // Set once a batch is rejected for being too large, limiting subsequent batches to the event
+    // store's max batch size, or to half the batch's size if the event store didn't specify one.
+    // The limit is doubled after each `BATCH_SIZE_LIMIT_RECOVERY_FLUSHES` consecutive successful
+    // flushes until it is removed, so that it recovers if the cause was temporary.
+    #[serde(skip)]
+    batch_size_limit: Option<usize>,
+    #[serde(skip)]
+    flushes_since_batch_size_limited: u32,
+    #[serde(default)]
+    total_batches_split: u64,
+    #[serde(default = "default_bisect_after_failures")]
+    bisect_after_failures: Option<u32>,
+    // The lengths of the suspect batches at the front of `events`. Once a batch has failed
+    // `bisect_after_failures` times it is split in two, each half being flushed as a batch of its
+    // own and split again if it fails, until the events causing the failure are isolated.
+    #[serde(default)]
+    quarantined_batches: VecDeque<usize>,
+    // Whether any flush has succeeded since the current bisection began. Until one has, the
+    // failures may be due to the event store being unavailable rather than the events themselves.
+    #[serde(skip)]
+    flush_succeeded_while_bisecting: bool,
+    #[serde(default)]
+    dead_letters: Vec<IdempotentEvent>,
+    #[serde(default = "default_max_dead_letters")]
+    max_dead_letters: usize,
+    #[serde(default)]
+    last_flush_error: Option<FlushError>,
+    #[serde(default)]
+    last_flush_error_at: Option<TimestampMillis>,
+}
+
+fn default_max_concurrent_batches() -> usize {
+    DEFAULT_MAX_CONCURRENT_BATCHES as usize
+}
+
+fn default_max_batch_bytes() -> u64 {
+    DEFAULT_MAX_BATCH_BYTES
+}
+
+fn default_bisect_after_failures() -> Option<u32> {
+    Some(DEFAULT_BISECT_AFTER_FAILURES)
+}
+
+fn default_max_dead_letters() -> usize {
+    DEFAULT_MAX_DEAD_LETTERS as usize
+}
+
+// Persists buffered events so that they survive the process restarting. Events are appended as
+// they are pushed and acknowledged once the client no longer holds them, either because they were
+// flushed, dropped or taken via `take_events`. Events which were appended but never acknowledged
+// are recovered when the client is built and are flushed with their original idempotency keys.
+pub trait Spool {
+    fn append(&mut self, events: &[IdempotentEvent]) -> std::io::Result<()>;
+    fn acknowledge(&mut self, idempotency_keys: &[u128]) -> std::io::Result<()>;
+    fn recover(&mut self) -> std::io::Result<Vec<IdempotentEvent>>;
+}
+
+#[derive(Clone, Debug)]
+pub struct ShutdownReport {
+    // Events which were still buffered when the timeout expired. They have been removed from the
+    // client so that the caller can decide what to do with them. If a spool is in use, they remain
+    // in the spool and will be recovered the next time the client is built.
+    pub undelivered: Vec<IdempotentEvent>,
+    // Events whose flush was still in progress when the timeout expired, these may or may not be
+    // delivered. If their flush fails and a spool is in use, they remain in the spool.
+    pub events_in_flight: u32,
+}
+
+// Determines which events are removed once the buffer exceeds `max_buffered_events` or
+// `max_buffered_bytes`. Events in a batch which is being flushed don't count towards the limits.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    #[default]
+    DropOldest,
+    DropNewest,
+    // Drops the event whose name has the lowest priority, the oldest being dropped in the case of
+    // a tie. Priorities are set via `with_event_priority`, other events use
+    // `DEFAULT_EVENT_PRIORITY`.
+    DropLowestPriority,
+    // New events are rejected rather than buffered. They are counted separately to dropped events.
+    Reject,
+}
+
+// Controls how failed flushes are retried. The delay before the nth consecutive retry is
+// `initial_delay * multiplier^(n-1)`, capped at `max_delay`, then reduced by a random proportion
+// of up to `jitter` (between 0 and 1) so that producers which failed together don't retry
+// together. Once `max_attempts` consecutive flushes have failed, the failed batch is abandoned.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    pub initial_delay: Duration,
+    pub multiplier: f64,
+    pub max_delay: Duration,
+    pub jitter: f64,
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            initial_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            max_delay: DEFAULT_FLUSH_DELAY,
+            jitter: 0.2,
+            max_attempts: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PushError {
+    // The client is in use by another thread, eg. while a flush completes
+    Busy,
+    // The buffer is full and the overflow policy is `OverflowPolicy::Reject`
+    BufferFull,
+    // The client has been shut down
+    ShutDown,
+}
+
+impl Display for PushError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PushError::Busy => f.write_str("Event store client is busy"),
+            PushError::BufferFull => f.write_str("Event store client buffer is full"),
+            PushError::ShutDown => f.write_str("Event store client has been shut down"),
+        }
+    }
+}
+
+impl std::error::Error for PushError {}
+
+impl RetryPolicy {
+    // The delay after `consecutive_failures` failed flushes, with the jitter determined by `random`
+    pub fn retry_delay(&self, consecutive_failures: u32, random: u128) -> Duration {
+        let exponent = consecutive_failures.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay_secs = (self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent))
+            .min(self.max_delay.as_secs_f64());
+        let delay = Duration::try_from_secs_f64(delay_secs).unwrap_or(self.max_delay);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter > 0.0 {
+            let random = (random % JITTER_RESOLUTION) as f64 / JITTER_RESOLUTION as f64;
+            delay.mul_f64(1.0 - jitter * random)
+        } else {
+            delay
+        }
+    }
+
+    // Whether a batch should be abandoned after `consecutive_failures` failed flushes
+    pub fn attempts_exhausted(&self, consecutive_failures: u32) -> bool {
+        self.max_attempts
+            .is_some_and(|max| consecutive_failures >= max)
+    }
 }
 
 pub trait Runtime {
     fn schedule_flush<F: FnOnce() + Send + 'static>(&mut self, delay: Duration, callback: F);
+    // Events are pushed to the default stream if `stream` is None
     fn flush<F: FnOnce(FlushOutcome) + Send + 'static>(
         &mut self,
         event_store_canister_id: Principal,
+        stream: Option<String>,
         events: Vec<IdempotentEvent>,
         on_complete: F,
     );
@@ -50,13 +340,32 @@ pub trait Runtime {
     fn now(&self) -> TimestampMillis;
 }
 
+// All methods are safe to call from multiple threads. Methods other than `try_push` wait for the
+// lock, which is only ever held briefly, the exception being when a spool is in use, since events
+// are written to the spool while the lock is held. Eg. `FileSpool` syncs each push to disk, so
+// each push then takes as long as an fsync and concurrent pushes wait for each other's syncs.
 impl<R> Client<R> {
     pub fn take_events(&mut self) -> Vec<IdempotentEvent> {
-        mem::take(&mut self.inner.try_lock().unwrap().events)
+        let mut guard = self.lock();
+        guard.buffered_bytes = 0;
+        guard.quarantined_batches.clear();
+        let events = mem::take(&mut guard.events);
+        guard.spool_acknowledge(&events);
+        events
+    }
+
+    // Takes the events which were isolated as causing flushes to fail. At most `max_dead_letters`
+    // are held, beyond that the oldest are dropped and counted in `total_events_dropped`, so they
+    // should be taken regularly.
+    pub fn take_dead_letters(&mut self) -> Vec<IdempotentEvent> {
+        let mut guard = self.lock();
+        let dead_letters = mem::take(&mut guard.dead_letters);
+        guard.spool_acknowledge(&dead_letters);
+        dead_letters
     }
 
     pub fn info(&self) -> EventStoreClientInfo {
-        let guard = self.inner.try_lock().unwrap();
+        let guard = self.lock();
 
         EventStoreClientInfo {
             event_store_canister_id: guard.event_store_canister_id,
@@ -66,6 +375,45 @@ impl<R> Client<R> {
             flush_in_progress: guard.flush_in_progress,
             next_flush_scheduled: guard.next_flush_scheduled,
             total_events_flushed: guard.total_events_flushed,
+            retry_policy: guard.retry_policy,
+            consecutive_failures: guard.consecutive_failures,
+            total_events_abandoned: guard.total_events_abandoned,
+            total_events_rejected_by_store: guard.total_events_rejected_by_store,
+            events_pending_bytes: guard.buffered_bytes,
+            max_buffered_events: guard.max_buffered_events,
+            max_buffered_bytes: guard.max_buffered_bytes,
+            overflow_policy: guard.overflow_policy,
+            total_events_dropped: guard.total_events_dropped,
+            total_events_rejected: guard.total_events_rejected,
+            events_in_flight: guard.events_in_flight as u32,
+            shut_down: guard.shut_down,
+            spool_enabled: guard.spool.is_some(),
+            spool_errors: guard.spool_errors,
+            max_concurrent_batches: guard.max_concurrent_batches as u32,
+            batches_in_flight: guard.batches_in_flight as u32,
+            max_batch_bytes: guard.max_batch_bytes,
+            batch_size_limit: guard.batch_size_limit.map(|l| l as u32),
+            total_batches_split: guard.total_batches_split,
+            bisect_after_failures: guard.bisect_after_failures,
+            events_quarantined: guard.quarantined_batches.iter().sum::<usize>() as u32,
+            dead_letters: guard.dead_letters.len() as u32,
+            max_dead_letters: guard.max_dead_letters as u32,
+            last_flush_error: guard.last_flush_error.clone(),
+            last_flush_error_at: guard.last_flush_error_at,
+        }
+    }
+
+    // Recovers from poisoning rather than panicking, since a panic while the lock was held leaves
+    // the state usable
+    fn lock(&self) -> MutexGuard<ClientInner<R>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn try_lock(&self) -> Option<MutexGuard<ClientInner<R>>> {
+        match self.inner.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(error)) => Some(error.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
         }
     }
 }
@@ -78,9 +426,20 @@ impl Client<NullRuntime> {
 
 pub struct EventStoreClientBuilder<R> {
     event_store_canister_id: Principal,
+    stream: Option<String>,
     runtime: R,
     flush_delay: Option<Duration>,
     max_batch_size: Option<u32>,
+    retry_policy: Option<RetryPolicy>,
+    max_buffered_events: Option<u32>,
+    max_buffered_bytes: Option<u64>,
+    overflow_policy: Option<OverflowPolicy>,
+    event_priorities: BTreeMap<String, u8>,
+    spool: Option<Box<dyn Spool + Send>>,
+    max_concurrent_batches: Option<u32>,
+    max_batch_bytes: Option<u64>,
+    bisect_after_failures: Option<Option<u32>>,
+    max_dead_letters: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -92,15 +451,84 @@ pub struct EventStoreClientInfo {
     pub flush_in_progress: bool,
     pub next_flush_scheduled: Option<TimestampMillis>,
     pub total_events_flushed: u64,
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    // Events dropped after `max_attempts` consecutive failed flushes
+    #[serde(default)]
+    pub total_events_abandoned: u64,
+    // Events dropped because the event store rejected their batch in a way which retrying
+    // wouldn't fix, see `FlushOutcome::FailedShouldntRetry`
+    #[serde(default)]
+    pub total_events_rejected_by_store: u64,
+    #[serde(default)]
+    pub events_pending_bytes: u64,
+    #[serde(default)]
+    pub max_buffered_events: Option<u32>,
+    #[serde(default)]
+    pub max_buffered_bytes: Option<u64>,
+    #[serde(default)]
+    pub overflow_policy: OverflowPolicy,
+    #[serde(default)]
+    pub total_events_dropped: u64,
+    #[serde(default)]
+    pub total_events_rejected: u64,
+    #[serde(default)]
+    pub events_in_flight: u32,
+    #[serde(default)]
+    pub shut_down: bool,
+    #[serde(default)]
+    pub spool_enabled: bool,
+    // The number of spool operations which failed
+    #[serde(default)]
+    pub spool_errors: u64,
+    #[serde(default)]
+    pub max_concurrent_batches: u32,
+    #[serde(default)]
+    pub batches_in_flight: u32,
+    #[serde(default)]
+    pub max_batch_bytes: u64,
+    // The reduced batch size in use after batches were rejected for being too large
+    #[serde(default)]
+    pub batch_size_limit: Option<u32>,
+    #[serde(default)]
+    pub total_batches_split: u64,
+    #[serde(default)]
+    pub bisect_after_failures: Option<u32>,
+    // Buffered events in batches which are being bisected to find the events causing them to fail
+    #[serde(default)]
+    pub events_quarantined: u32,
+    // Events isolated by bisection which are awaiting `take_dead_letters`
+    #[serde(default)]
+    pub dead_letters: u32,
+    #[serde(default)]
+    pub max_dead_letters: u32,
+    // The error from the most recent failed flush, which isn't cleared by later successful flushes
+    #[serde(default)]
+    pub last_flush_error: Option<FlushError>,
+    #[serde(default)]
+    pub last_flush_error_at: Option<TimestampMillis>,
 }
 
 impl<R: Runtime + Send + 'static> ClientBuilder<R> {
     pub fn new(event_store_canister_id: Principal, runtime: R) -> ClientBuilder<R> {
         ClientBuilder {
             event_store_canister_id,
+            stream: None,
             runtime,
             flush_delay: None,
             max_batch_size: None,
+            retry_policy: None,
+            max_buffered_events: None,
+            max_buffered_bytes: None,
+            overflow_policy: None,
+            event_priorities: BTreeMap::new(),
+            spool: None,
+            max_concurrent_batches: None,
+            max_batch_bytes: None,
+            bisect_after_failures: None,
+            max_dead_letters: None,
         }
     }
 
@@ -114,79 +542,302 @@ impl<R: Runtime + Send + 'static> ClientBuilder<R> {
         self
     }
 
+    // Limits each batch by the estimated Candid encoded size of its events as well as by count. A
+    // single event larger than this is still flushed, in a batch of its own.
+    pub fn with_max_batch_bytes(mut self, max_batch_bytes: u64) -> Self {
+        self.max_batch_bytes = Some(max_batch_bytes);
+        self
+    }
+
+    // Once a batch has failed this many consecutive times with a failure which may have been
+    // caused by its events (see `FlushError::may_be_caused_by_events`) it is bisected to isolate
+    // any events which cause every flush containing them to fail. A single event which still fails
+    // after a flush has succeeded since the bisection began is moved to the dead letters, see
+    // `take_dead_letters`. Passing `None` disables bisection.
+    pub fn with_bisect_after_failures(mut self, failures: Option<u32>) -> Self {
+        self.bisect_after_failures = Some(failures.map(|f| f.max(1)));
+        self
+    }
+
+    // Dead letters don't count towards the buffer limits, instead once there are more than this
+    // many the oldest are dropped
+    pub fn with_max_dead_letters(mut self, max_dead_letters: u32) -> Self {
+        self.max_dead_letters = Some(max_dead_letters);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    pub fn with_max_buffered_events(mut self, max_buffered_events: u32) -> Self {
+        self.max_buffered_events = Some(max_buffered_events);
+        self
+    }
+
+    // Based on an estimate of each event's encoded size
+    pub fn with_max_buffered_bytes(mut self, max_buffered_bytes: u64) -> Self {
+        self.max_buffered_bytes = Some(max_buffered_bytes);
+        self
+    }
+
+    pub fn with_overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.overflow_policy = Some(overflow_policy);
+        self
+    }
+
+    // Pushes events to the named stream rather than the default stream. Idempotency keys are
+    // deduplicated per stream by the event store.
+    pub fn with_stream(mut self, stream: impl Into<String>) -> Self {
+        self.stream = Some(stream.into());
+        self
+    }
+
+    pub fn with_event_priority(mut self, event_name: impl Into<String>, priority: u8) -> Self {
+        self.event_priorities.insert(event_name.into(), priority);
+        self
+    }
+
+    // Allows up to this many batches to be flushed concurrently (default 1). Events within a batch
+    // are always delivered in the order they were pushed, and with a single batch in flight so are
+    // the batches themselves. With more than one, batches may complete out of order, and a failed
+    // batch is re-queued ahead of any events still buffered, so later events may be delivered
+    // before earlier ones which had to be retried.
+    pub fn with_max_concurrent_batches(mut self, max_concurrent_batches: u32) -> Self {
+        self.max_concurrent_batches = Some(max_concurrent_batches.max(1));
+        self
+    }
+
+    pub fn with_spool(mut self, spool: impl Spool + Send + 'static) -> Self {
+        self.spool = Some(Box::new(spool));
+        self
+    }
+
     pub fn build(self) -> Client<R> {
         let flush_delay = self.flush_delay.unwrap_or(DEFAULT_FLUSH_DELAY);
         let max_batch_size = self.max_batch_size.unwrap_or(DEFAULT_MAX_BATCH_SIZE) as usize;
+        let retry_policy = self.retry_policy.unwrap_or_default();
 
-        Client {
-            inner: Arc::new(Mutex::new(ClientInner::new(
-                self.event_store_canister_id,
-                self.runtime,
-                flush_delay,
-                max_batch_size,
-            ))),
+        let mut inner = ClientInner::new(
+            self.event_store_canister_id,
+            self.runtime,
+            flush_delay,
+            max_batch_size,
+            retry_policy,
+        );
+        inner.stream = self.stream;
+        inner.max_buffered_events = self.max_buffered_events;
+        inner.max_buffered_bytes = self.max_buffered_bytes;
+        inner.overflow_policy = self.overflow_policy.unwrap_or_default();
+        inner.event_priorities = self.event_priorities;
+        inner.spool = self.spool;
+        inner.max_concurrent_batches =
+            self.max_concurrent_batches
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_BATCHES) as usize;
+        inner.max_batch_bytes = self.max_batch_bytes.unwrap_or(DEFAULT_MAX_BATCH_BYTES);
+        inner.bisect_after_failures = self
+            .bisect_after_failures
+            .unwrap_or_else(default_bisect_after_failures);
+        inner.max_dead_letters = self
+            .max_dead_letters
+            .map_or_else(default_max_dead_letters, |max| max as usize);
+        inner.recover_spooled_events();
+        let any_events = !inner.events.is_empty();
+
+        let client = Client {
+            inner: Arc::new(Mutex::new(inner)),
+            flush_completed: Arc::default(),
+        };
+
+        if any_events {
+            let guard = client.lock();
+            client.process_events(guard, false);
         }
+        client
     }
 }
 
 impl<R: Runtime + Send + 'static> Client<R> {
+    // Events rejected due to the buffer being full are counted in `total_events_rejected`, use
+    // `try_push` to be notified of them instead
     pub fn push(&mut self, event: Event) {
-        let mut guard = self.inner.try_lock().unwrap();
-        let idempotency_key = guard.runtime.rng();
-        guard.events.push(event.to_idempotent(idempotency_key));
-        self.process_events(guard, true);
+        let guard = self.lock();
+        let _ = self.push_within_lock(guard, event);
+    }
+
+    // Returns `PushError::Busy` rather than waiting if the client is in use by another thread
+    pub fn try_push(&mut self, event: Event) -> Result<(), PushError> {
+        match self.try_lock() {
+            Some(guard) => self.push_within_lock(guard, event),
+            None => Err(PushError::Busy),
+        }
+    }
+
+    // Pushes straight away unless the client is in use by another thread (eg. while the spool is
+    // syncing), in which case a separate thread waits for the lock and pushes the event, waking the
+    // task once done, so that the executor's thread isn't blocked. Works with any executor.
+    pub async fn push_async(&mut self, event: Event) -> Result<(), PushError> {
+        if let Some(guard) = self.try_lock() {
+            return self.push_within_lock(guard, event);
+        }
+        PushOnThread::spawn(self.clone(), event).await
     }
 
     pub fn push_many(&mut self, events: impl Iterator<Item = Event>, can_flush_immediately: bool) {
-        let mut guard = self.inner.try_lock().unwrap();
+        let mut guard = self.lock();
+        if guard.shut_down {
+            guard.total_events_rejected += events.count() as u64;
+            return;
+        }
+        let events: Vec<_> = events
+            .map(|event| event.to_idempotent(guard.runtime.rng()))
+            .collect();
+        guard.spool_append(&events);
         for event in events {
-            let idempotency_key = guard.runtime.rng();
-            guard.events.push(event.to_idempotent(idempotency_key));
+            guard.buffer_event(event);
         }
         self.process_events(guard, can_flush_immediately);
     }
 
+    // Flushes all buffered events, one batch after another, without waiting for the flush delay.
+    // Failed batches are still retried according to the retry policy.
+    pub fn flush_now(&mut self) {
+        let mut guard = self.lock();
+        if !guard.events.is_empty() {
+            guard.flush_all = true;
+            self.process_events(guard, true);
+        }
+    }
+
+    // Flushes all buffered events then waits until they have been delivered (or abandoned) or
+    // until the timeout expires, whichever is sooner. Returns false if the timeout expired first.
+    // This blocks the calling thread, so when using tokio it should be called via `spawn_blocking`.
+    pub fn flush_and_wait(&mut self, timeout: Duration) -> bool {
+        let (guard, flushed) = self.flush_and_wait_within_lock(timeout);
+        drop(guard);
+        flushed
+    }
+
+    // Flushes all buffered events then waits until they have been delivered or until the timeout
+    // expires, whichever is sooner. Any events pushed afterwards are rejected. This blocks the
+    // calling thread, so when using tokio it should be called via `spawn_blocking`.
+    pub fn shutdown(&mut self, timeout: Duration) -> ShutdownReport {
+        let (mut guard, _) = self.flush_and_wait_within_lock(timeout);
+
+        guard.shut_down = true;
+        guard.flush_all = false;
+        guard.buffered_bytes = 0;
+        guard.quarantined_batches.clear();
+
+        ShutdownReport {
+            undelivered: mem::take(&mut guard.events),
+            events_in_flight: guard.events_in_flight as u32,
+        }
+    }
+
+    fn flush_and_wait_within_lock(
+        &mut self,
+        timeout: Duration,
+    ) -> (MutexGuard<ClientInner<R>>, bool) {
+        self.flush_now();
+
+        let guard = self.lock();
+        let (guard, result) = self
+            .flush_completed
+            .wait_timeout_while(guard, timeout, |inner| {
+                !inner.events.is_empty() || inner.batches_in_flight > 0
+            })
+            .unwrap_or_else(PoisonError::into_inner);
+
+        (guard, !result.timed_out())
+    }
+
+    fn push_within_lock(
+        &self,
+        mut guard: MutexGuard<ClientInner<R>>,
+        event: Event,
+    ) -> Result<(), PushError> {
+        if guard.shut_down {
+            guard.total_events_rejected += 1;
+            return Err(PushError::ShutDown);
+        }
+        let idempotency_key = guard.runtime.rng();
+        let event = event.to_idempotent(idempotency_key);
+        guard.spool_append(std::slice::from_ref(&event));
+        let accepted = guard.buffer_event(event);
+        self.process_events(guard, true);
+
+        if accepted {
+            Ok(())
+        } else {
+            Err(PushError::BufferFull)
+        }
+    }
+
     fn flush_batch(&self) {
-        let guard = self.inner.try_lock().unwrap();
-        self.flush_batch_within_lock(guard);
+        let mut guard = self.lock();
+        guard.next_flush_scheduled = None;
+
+        if guard.shut_down || !guard.is_batch_slot_available() {
+            // The next batch will be flushed once one of those in flight completes
+            return;
+        }
+        self.start_batch(&mut guard);
+
+        // While retrying, only a single batch is flushed until one succeeds
+        if guard.consecutive_failures == 0 {
+            self.process_events(guard, true);
+        }
     }
 
-    fn flush_batch_within_lock(&self, mut guard: MutexGuard<ClientInner<R>>) {
+    fn start_batch(&self, guard: &mut MutexGuard<ClientInner<R>>) {
         guard.next_flush_scheduled = None;
 
         if !guard.events.is_empty() {
+            let (events, quarantined) = guard.take_batch();
+            guard.batches_in_flight += 1;
             guard.flush_in_progress = true;
+            guard.events_in_flight += events.len();
 
-            let max_batch_size = guard.max_batch_size;
-
-            let events = if guard.events.len() <= max_batch_size {
-                mem::take(&mut guard.events)
-            } else {
-                guard.events.drain(..max_batch_size).collect()
-            };
-
+            // Each batch carries its own events, so its outcome is handled independently of any
+            // other batches in flight
             let mut clone = self.clone();
             let event_store_canister_id = guard.event_store_canister_id;
-            guard
-                .runtime
-                .flush(event_store_canister_id, events.clone(), move |outcome| {
-                    clone.on_flush_complete(outcome, events)
-                });
+            let stream = guard.stream.clone();
+            guard.runtime.flush(
+                event_store_canister_id,
+                stream,
+                events.clone(),
+                move |outcome| clone.on_flush_complete(outcome, events, quarantined),
+            );
         }
     }
 
-    fn process_events(&self, guard: MutexGuard<ClientInner<R>>, can_flush_immediately: bool) {
-        if guard.flush_in_progress {
+    fn process_events(&self, mut guard: MutexGuard<ClientInner<R>>, can_flush_immediately: bool) {
+        if guard.shut_down || !guard.is_batch_slot_available() {
             return;
         }
-        let max_batch_size_reached = guard.events.len() >= guard.max_batch_size;
-        if max_batch_size_reached {
-            if can_flush_immediately {
-                self.flush_batch_within_lock(guard);
-            } else {
-                self.schedule_flush(guard, Duration::ZERO)
+        // After a failed flush, wait for the backoff delay even if the batch is full
+        if guard.consecutive_failures > 0 {
+            if guard.next_flush_scheduled.is_none() {
+                let delay = guard.next_retry_delay();
+                self.schedule_flush(guard, delay);
+            }
+            return;
+        }
+        // Fill the free slots with full batches, or with any remaining events when draining
+        while guard.is_batch_full() || (guard.flush_all && !guard.events.is_empty()) {
+            if !can_flush_immediately {
+                self.schedule_flush(guard, Duration::ZERO);
+                return;
             }
-        } else if guard.next_flush_scheduled.is_none() {
+            self.start_batch(&mut guard);
+            if !guard.is_batch_slot_available() {
+                return;
+            }
+        }
+        if !guard.events.is_empty() && guard.next_flush_scheduled.is_none() {
             let delay = guard.flush_delay;
             self.schedule_flush(guard, delay)
         }
@@ -201,14 +852,19 @@ impl<R: Runtime + Send + 'static> Client<R> {
         guard.next_flush_scheduled = Some(now + delay.as_millis() as u64);
     }
 
-    fn on_flush_complete(&mut self, outcome: FlushOutcome, events: Vec<IdempotentEvent>) {
+    fn on_flush_complete(
+        &mut self,
+        outcome: FlushOutcome,
+        events: Vec<IdempotentEvent>,
+        quarantined: bool,
+    ) {
         if let Ok(guard) = self.inner.try_lock() {
-            self.on_flush_within_lock(guard, outcome, events);
+            self.on_flush_within_lock(guard, outcome, events, quarantined);
         } else {
             let clone = self.clone();
             thread::spawn(move || {
-                let guard = clone.inner.lock().unwrap();
-                clone.on_flush_within_lock(guard, outcome, events);
+                let guard = clone.lock();
+                clone.on_flush_within_lock(guard, outcome, events, quarantined);
             });
         }
     }
@@ -218,23 +874,109 @@ impl<R: Runtime + Send + 'static> Client<R> {
         mut guard: MutexGuard<ClientInner<R>>,
         outcome: FlushOutcome,
         events: Vec<IdempotentEvent>,
+        quarantined: bool,
     ) {
-        guard.flush_in_progress = false;
+        guard.batches_in_flight -= 1;
+        guard.flush_in_progress = guard.batches_in_flight > 0;
+        guard.events_in_flight -= events.len();
+
+        if let Some(error) = outcome.error() {
+            guard.last_flush_error = Some(error.clone());
+            guard.last_flush_error_at = Some(guard.runtime.now());
+        }
 
         match outcome {
-            FLUSH_OUTCOME_SUCCESS => {
+            FlushOutcome::Success => {
+                guard.consecutive_failures = 0;
+                guard.consecutive_event_failures = 0;
+                guard.flush_succeeded_while_bisecting = true;
                 guard.total_events_flushed = guard
                     .total_events_flushed
                     .saturating_add(events.len() as u64);
+                guard.spool_acknowledge(&events);
+                guard.relax_batch_size_limit();
             }
-            FLUSH_OUTCOME_FAILED_SHOULD_RETRY => {
-                guard.events.extend(events);
+            FlushOutcome::FailedShouldRetry(error) => {
+                guard.consecutive_failures += 1;
+                let caused_by_events = error.may_be_caused_by_events();
+                if caused_by_events {
+                    guard.consecutive_event_failures += 1;
+                }
+
+                if guard.shut_down {
+                    // Once shut down no further flushes will happen, so the events can't be
+                    // retried. They are left in the spool so that they are recovered the next
+                    // time the client is built.
+                    guard.consecutive_failures = 0;
+                    guard.consecutive_event_failures = 0;
+                    guard.total_events_abandoned = guard
+                        .total_events_abandoned
+                        .saturating_add(events.len() as u64);
+                } else if guard
+                    .retry_policy
+                    .attempts_exhausted(guard.consecutive_failures)
+                {
+                    guard.consecutive_failures = 0;
+                    guard.consecutive_event_failures = 0;
+                    guard.total_events_abandoned = guard
+                        .total_events_abandoned
+                        .saturating_add(events.len() as u64);
+                    guard.spool_acknowledge(&events);
+                } else if caused_by_events
+                    && (quarantined
+                        || guard
+                            .bisect_after_failures
+                            .is_some_and(|failures| guard.consecutive_event_failures >= failures))
+                {
+                    guard.bisect(events, quarantined);
+                } else if quarantined {
+                    // Eg. the event store was rate limiting, so the batch is retried as it was
+                    let len = events.len();
+                    guard.requeue_quarantined(events, &[len]);
+                } else {
+                    guard.requeue(events);
+                }
             }
-            _ => {}
+            FlushOutcome::FailedBatchTooLarge(error) if events.len() > 1 => {
+                // The event store's limit is only used if it would split the batch, otherwise the
+                // batch was too large in bytes rather than in events
+                let limit = error
+                    .max_batch_size
+                    .map(|max| max as usize)
+                    .filter(|max| (1..events.len()).contains(max))
+                    .unwrap_or(events.len() / 2);
+                guard.batch_size_limit =
+                    Some(guard.batch_size_limit.map_or(limit, |l| l.min(limit)));
+                guard.flushes_since_batch_size_limited = 0;
+                guard.total_batches_split += 1;
+                guard.requeue(events);
+            }
+            // A single event which is too large can never be delivered
+            FlushOutcome::FailedBatchTooLarge(_) => {
+                guard.total_events_abandoned = guard
+                    .total_events_abandoned
+                    .saturating_add(events.len() as u64);
+                guard.spool_acknowledge(&events);
+            }
+            FlushOutcome::FailedShouldntRetry(_) => {
+                guard.consecutive_failures = 0;
+                guard.consecutive_event_failures = 0;
+                guard.total_events_rejected_by_store = guard
+                    .total_events_rejected_by_store
+                    .saturating_add(events.len() as u64);
+                guard.spool_acknowledge(&events);
+            }
+        }
+
+        if guard.events.is_empty() {
+            guard.flush_all = false;
         }
+        self.flush_completed.notify_all();
 
         if !guard.events.is_empty() {
-            self.process_events(guard, false);
+            // When draining, the next batch is flushed straight away rather than via a timer
+            let flush_all = guard.flush_all;
+            self.process_events(guard, flush_all);
         }
     }
 }
@@ -243,6 +985,7 @@ impl<R> Clone for Client<R> {
     fn clone(&self) -> Self {
         Client {
             inner: self.inner.clone(),
+            flush_completed: self.flush_completed.clone(),
         }
     }
 }
@@ -253,9 +996,11 @@ impl<R> ClientInner<R> {
         runtime: R,
         flush_delay: Duration,
         max_batch_size: usize,
+        retry_policy: RetryPolicy,
     ) -> ClientInner<R> {
         ClientInner {
             event_store_canister_id,
+            stream: None,
             runtime,
             flush_delay,
             max_batch_size,
@@ -263,6 +1008,255 @@ impl<R> ClientInner<R> {
             next_flush_scheduled: None,
             flush_in_progress: false,
             total_events_flushed: 0,
+            retry_policy,
+            consecutive_failures: 0,
+            consecutive_event_failures: 0,
+            total_events_abandoned: 0,
+            total_events_rejected_by_store: 0,
+            max_buffered_events: None,
+            max_buffered_bytes: None,
+            overflow_policy: OverflowPolicy::default(),
+            event_priorities: BTreeMap::new(),
+            buffered_bytes: 0,
+            total_events_dropped: 0,
+            total_events_rejected: 0,
+            flush_all: false,
+            events_in_flight: 0,
+            shut_down: false,
+            spool: None,
+            spool_errors: 0,
+            max_concurrent_batches: DEFAULT_MAX_CONCURRENT_BATCHES as usize,
+            batches_in_flight: 0,
+            max_batch_bytes: DEFAULT_MAX_BATCH_BYTES,
+            batch_size_limit: None,
+            flushes_since_batch_size_limited: 0,
+            total_batches_split: 0,
+            bisect_after_failures: default_bisect_after_failures(),
+            quarantined_batches: VecDeque::new(),
+            flush_succeeded_while_bisecting: false,
+            dead_letters: Vec::new(),
+            max_dead_letters: default_max_dead_letters(),
+            last_flush_error: None,
+            last_flush_error_at: None,
+        }
+    }
+
+    fn is_batch_slot_available(&self) -> bool {
+        self.batches_in_flight < self.max_concurrent_batches
+    }
+
+    // Returns false if the event was rejected
+    fn buffer_event(&mut self, event: IdempotentEvent) -> bool {
+        self.buffered_bytes += estimated_event_size(&event);
+        self.events.push(event);
+        let rejected_before = self.total_events_rejected;
+        self.enforce_buffer_limits(true);
+        self.total_events_rejected == rejected_before
+    }
+
+    // Failed events are older than any events pushed since, so they go back to the front, behind
+    // any quarantined batches so that `quarantined_batches` still describes the front of the
+    // buffer. If the buffer has filled up in the meantime, events are dropped according to the
+    // overflow policy.
+    fn requeue(&mut self, events: Vec<IdempotentEvent>) {
+        let index = self.quarantined_batches.iter().sum();
+        self.insert_events(index, events);
+    }
+
+    // Returns a quarantined batch to the very front of the buffer, to be flushed as batches of the
+    // given lengths
+    fn requeue_quarantined(&mut self, events: Vec<IdempotentEvent>, batch_lengths: &[usize]) {
+        for len in batch_lengths.iter().rev().filter(|len| **len > 0) {
+            self.quarantined_batches.push_front(*len);
+        }
+        self.insert_events(0, events);
+    }
+
+    fn insert_events(&mut self, index: usize, events: Vec<IdempotentEvent>) {
+        self.buffered_bytes += events.iter().map(estimated_event_size).sum::<u64>();
+        self.events.splice(index..index, events);
+        self.enforce_buffer_limits(false);
+    }
+
+    fn enforce_buffer_limits(&mut self, new_event_pushed: bool) {
+        while self.is_buffer_over_limit() {
+            let index = match self.overflow_policy {
+                OverflowPolicy::DropOldest => 0,
+                OverflowPolicy::DropNewest | OverflowPolicy::Reject => self.events.len() - 1,
+                OverflowPolicy::DropLowestPriority => self.lowest_priority_index(),
+            };
+            let removed = self.events.remove(index);
+            self.remove_from_quarantine(index);
+            self.buffered_bytes -= estimated_event_size(&removed);
+            self.spool_acknowledge(std::slice::from_ref(&removed));
+
+            if new_event_pushed && self.overflow_policy == OverflowPolicy::Reject {
+                self.total_events_rejected += 1;
+            } else {
+                self.total_events_dropped += 1;
+            }
+        }
+    }
+
+    fn recover_spooled_events(&mut self) {
+        let Some(spool) = self.spool.as_mut() else {
+            return;
+        };
+        match spool.recover() {
+            Ok(events) => {
+                for event in events {
+                    self.buffer_event(event);
+                }
+            }
+            Err(_) => self.spool_errors += 1,
+        }
+    }
+
+    fn spool_append(&mut self, events: &[IdempotentEvent]) {
+        if let Some(spool) = self.spool.as_mut() {
+            if spool.append(events).is_err() {
+                self.spool_errors += 1;
+            }
+        }
+    }
+
+    fn spool_acknowledge(&mut self, events: &[IdempotentEvent]) {
+        if let Some(spool) = self.spool.as_mut() {
+            let keys: Vec<_> = events.iter().map(|e| e.idempotency_key).collect();
+            if spool.acknowledge(&keys).is_err() {
+                self.spool_errors += 1;
+            }
+        }
+    }
+
+    // Splits the failed batch in two, quarantining each half so that they are flushed separately.
+    // A single event is moved to the dead letters if it is still failing after another flush has
+    // succeeded, otherwise it is quarantined by itself and retried.
+    fn bisect(&mut self, events: Vec<IdempotentEvent>, quarantined: bool) {
+        if !quarantined && self.quarantined_batches.is_empty() {
+            self.flush_succeeded_while_bisecting = false;
+        }
+
+        if events.len() == 1 && quarantined && self.flush_succeeded_while_bisecting {
+            self.dead_letters.extend(events);
+            let excess = self
+                .dead_letters
+                .len()
+                .saturating_sub(self.max_dead_letters);
+            if excess > 0 {
+                let dropped: Vec<_> = self.dead_letters.drain(..excess).collect();
+                self.total_events_dropped += excess as u64;
+                self.spool_acknowledge(&dropped);
+            }
+            return;
+        }
+
+        let second_half = events.len() / 2;
+        let first_half = events.len() - second_half;
+        self.requeue_quarantined(events, &[first_half, second_half]);
+    }
+
+    // Keeps the quarantined batch lengths in sync when the event at `index` is removed
+    fn remove_from_quarantine(&mut self, index: usize) {
+        let mut start = 0;
+        for i in 0..self.quarantined_batches.len() {
+            let len = self.quarantined_batches[i];
+            if index < start + len {
+                if len == 1 {
+                    self.quarantined_batches.remove(i);
+                } else {
+                    self.quarantined_batches[i] -= 1;
+                }
+                return;
+            }
+            start += len;
+        }
+    }
+
+    // Quarantined batches are flushed before any other events
+    fn take_batch(&mut self) -> (Vec<IdempotentEvent>, bool) {
+        if let Some(len) = self.quarantined_batches.pop_front() {
+            let batch: Vec<_> = self.events.drain(..len).collect();
+            let batch_bytes: u64 = batch.iter().map(estimated_event_size).sum();
+            self.buffered_bytes = self.buffered_bytes.saturating_sub(batch_bytes);
+            return (batch, true);
+        }
+
+        let max_events = self.effective_max_batch_size();
+        let mut batch_len = 0;
+        let mut batch_bytes = 0;
+        for event in self.events.iter() {
+            let size = estimated_event_size(event);
+            if batch_len == max_events
+                || (batch_len > 0 && batch_bytes + size > self.max_batch_bytes)
+            {
+                break;
+            }
+            batch_len += 1;
+            batch_bytes += size;
+        }
+        self.buffered_bytes = self.buffered_bytes.saturating_sub(batch_bytes);
+        (self.events.drain(..batch_len).collect(), false)
+    }
+
+    fn relax_batch_size_limit(&mut self) {
+        let Some(limit) = self.batch_size_limit else {
+            return;
+        };
+        self.flushes_since_batch_size_limited += 1;
+        if self.flushes_since_batch_size_limited >= BATCH_SIZE_LIMIT_RECOVERY_FLUSHES {
+            self.flushes_since_batch_size_limited = 0;
+            let limit = limit.saturating_mul(2);
+            self.batch_size_limit = (limit < self.max_batch_size).then_some(limit);
+        }
+    }
+
+    fn effective_max_batch_size(&self) -> usize {
+        self.batch_size_limit
+            .map_or(self.max_batch_size, |limit| limit.min(self.max_batch_size))
+            .max(1)
+    }
+
+    // Quarantined batches are always flushed without waiting for the flush delay
+    fn is_batch_full(&self) -> bool {
+        !self.quarantined_batches.is_empty()
+            || self.events.len() >= self.effective_max_batch_size()
+            || self.buffered_bytes >= self.max_batch_bytes
+    }
+
+    fn is_buffer_over_limit(&self) -> bool {
+        self.max_buffered_events
+            .is_some_and(|max| self.events.len() > max as usize)
+            || self
+                .max_buffered_bytes
+                .is_some_and(|max| self.buffered_bytes > max)
+    }
+
+    fn lowest_priority_index(&self) -> usize {
+        self.events
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, e)| {
+                self.event_priorities
+                    .get(&e.name)
+                    .copied()
+                    .unwrap_or(DEFAULT_EVENT_PRIORITY)
+            })
+            .map(|(index, _)| index)
+            .unwrap_or_default()
+    }
+}
+
+impl<R: Runtime> ClientInner<R> {
+    fn next_retry_delay(&mut self) -> Duration {
+        let random = self.runtime.rng();
+        let delay = self
+            .retry_policy
+            .retry_delay(self.consecutive_failures, random);
+
+        match self.last_flush_error.as_ref().and_then(|e| e.retry_after) {
+            Some(retry_after) => delay.max(retry_after),
+            None => delay,
         }
     }
 }
@@ -272,7 +1266,7 @@ impl<R: Serialize> Serialize for Client<R> {
     where
         S: Serializer,
     {
-        let inner = self.inner.try_lock().unwrap();
+        let inner = self.lock();
         inner.serialize(serializer)
     }
 }
@@ -282,14 +1276,16 @@ impl<'de, R: Deserialize<'de> + Runtime + Send + 'static> Deserialize<'de> for C
     where
         D: Deserializer<'de>,
     {
-        let inner = ClientInner::deserialize(deserializer)?;
+        let mut inner = ClientInner::deserialize(deserializer)?;
+        inner.buffered_bytes = inner.events.iter().map(estimated_event_size).sum();
         let any_events = !inner.events.is_empty();
         let client = Client {
             inner: Arc::new(Mutex::new(inner)),
+            flush_completed: Arc::default(),
         };
 
         if any_events {
-            let guard = client.inner.try_lock().unwrap();
+            let guard = client.lock();
             client.process_events(guard, false);
         }
 
@@ -297,6 +1293,61 @@ impl<'de, R: Deserialize<'de> + Runtime + Send + 'static> Deserialize<'de> for C
     }
 }
 
+// A rough estimate of the event's Candid encoded size
+pub fn estimated_event_size(event: &IdempotentEvent) -> u64 {
+    let strings_len = event.name.len()
+        + event.user.as_ref().map_or(0, |u| u.as_str().len())
+        + event.source.as_ref().map_or(0, |s| s.as_str().len());
+
+    EVENT_SIZE_OVERHEAD + (strings_len + event.payload.len()) as u64
+}
+
+// Completes once the thread it spawns has pushed the event
+struct PushOnThread {
+    state: Arc<Mutex<PushOnThreadState>>,
+}
+
+#[derive(Default)]
+struct PushOnThreadState {
+    result: Option<Result<(), PushError>>,
+    waker: Option<Waker>,
+}
+
+impl PushOnThread {
+    fn spawn<R: Runtime + Send + 'static>(client: Client<R>, event: Event) -> PushOnThread {
+        let state: Arc<Mutex<PushOnThreadState>> = Arc::default();
+        let state_clone = state.clone();
+
+        thread::spawn(move || {
+            let guard = client.lock();
+            let result = client.push_within_lock(guard, event);
+
+            let mut state = state_clone.lock().unwrap_or_else(PoisonError::into_inner);
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+
+        PushOnThread { state }
+    }
+}
+
+impl Future for PushOnThread {
+    type Output = Result<(), PushError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
 pub struct NullRuntime;
 
 impl Runtime for NullRuntime {
@@ -305,10 +1356,11 @@ impl Runtime for NullRuntime {
     fn flush<F: FnOnce(FlushOutcome) + Send + 'static>(
         &mut self,
         _event_store_canister_id: Principal,
+        _stream: Option<String>,
         _events: Vec<IdempotentEvent>,
         on_complete: F,
     ) {
-        on_complete(FLUSH_OUTCOME_SUCCESS)
+        on_complete(FlushOutcome::Success)
     }
 
     fn rng(&mut self) -> u128 {
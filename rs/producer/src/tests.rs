@@ -1,7 +1,13 @@
-use crate::{ClientBuilder, FlushOutcome, Runtime};
+use crate::{
+    ClientBuilder, EventStoreClient, FlushError, FlushOutcome, OverflowPolicy, PushError,
+    RetryPolicy, Runtime, Spool,
+};
 use event_store_types::{EventBuilder, IdempotentEvent, TimestampMillis};
 use ic_principal::Principal;
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::ops::Range;
+use std::pin::pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Wake, Waker};
 use std::thread;
 use std::time::Duration;
 use test_case::test_case;
@@ -52,6 +58,892 @@ fn batch_flushed_when_flush_delay_reached(flush_synchronously: bool) {
     }
 }
 
+#[test_case(true)]
+#[test_case(false)]
+fn failed_flushes_retried_with_exponential_backoff(flush_synchronously: bool) {
+    let runtime = TestRuntime::new(flush_synchronously);
+    runtime.inner().flush_outcome = failed_should_retry();
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(5)
+        .with_retry_policy(RetryPolicy {
+            initial_delay: Duration::from_secs(1),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(3),
+            jitter: 0.0,
+            max_attempts: Some(4),
+        })
+        .with_bisect_after_failures(None)
+        .build();
+
+    for _ in 0..5 {
+        client.push(EventBuilder::new("event", 0).build());
+    }
+    runtime.tick();
+    thread::sleep(Duration::from_millis(10));
+
+    // Reaching the max batch size doesn't trigger an immediate flush while backing off
+    client.push(EventBuilder::new("event", 0).build());
+    assert_eq!(runtime.inner().flush_invocations, 1);
+
+    // Retries are scheduled after 1s, 2s then 3s (capped by `max_delay`)
+    for (attempt, due_at) in [(1, 1000), (2, 3000), (3, 6000)] {
+        let info = client.info();
+        assert_eq!(info.consecutive_failures, attempt);
+        assert_eq!(info.events_pending, 6);
+        assert_eq!(info.next_flush_scheduled, Some(due_at));
+        assert_eq!(runtime.inner().flush_invocations, attempt);
+
+        runtime.inner().timestamp = due_at;
+        runtime.tick();
+        runtime.tick();
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // The 4th failure reaches `max_attempts` so the batch is abandoned
+    let info = client.info();
+    assert_eq!(runtime.inner().flush_invocations, 4);
+    assert_eq!(info.consecutive_failures, 0);
+    assert_eq!(info.events_pending, 1);
+    assert_eq!(info.total_events_abandoned, 5);
+}
+
+#[test]
+fn events_flushed_to_configured_stream() {
+    let runtime = TestRuntime::new(true);
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(1)
+        .build();
+    let mut stream_client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(1)
+        .with_stream("ops")
+        .build();
+
+    client.push(EventBuilder::new("event", 0).build());
+    stream_client.push(EventBuilder::new("event", 0).build());
+    runtime.tick();
+
+    assert_eq!(
+        runtime.inner().flushed_streams,
+        vec![None, Some("ops".to_string())]
+    );
+}
+
+#[test]
+fn successful_flush_resets_backoff() {
+    let runtime = TestRuntime::new(false);
+    runtime.inner().flush_outcome = failed_should_retry();
+    runtime.inner().rng = 500_000;
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(1)
+        .with_retry_policy(RetryPolicy {
+            initial_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(60),
+            jitter: 0.5,
+            max_attempts: None,
+        })
+        .build();
+
+    client.push(EventBuilder::new("event", 0).build());
+    runtime.tick();
+
+    // The jitter of 0.5 scaled by the random value of 0.5 reduces the delay by 25%
+    let info = client.info();
+    assert_eq!(info.consecutive_failures, 1);
+    let due_at = info.next_flush_scheduled.unwrap();
+    assert_eq!(due_at, 7500);
+
+    runtime.inner().flush_outcome = FlushOutcome::Success;
+    runtime.inner().timestamp = due_at;
+    runtime.tick();
+    runtime.tick();
+
+    let info = client.info();
+    assert_eq!(info.consecutive_failures, 0);
+    assert_eq!(info.events_pending, 0);
+    assert_eq!(info.total_events_flushed, 1);
+}
+
+#[test]
+fn rate_limit_retry_after_is_minimum_retry_delay() {
+    let runtime = TestRuntime::new(false);
+    runtime.inner().flush_outcome = FlushOutcome::FailedShouldRetry(FlushError {
+        reject_code: None,
+        message: "Rate limit exceeded".to_string(),
+        retry_after: Some(Duration::from_secs(30)),
+        max_batch_size: None,
+    });
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(1)
+        .with_retry_policy(RetryPolicy {
+            initial_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(60),
+            jitter: 0.0,
+            max_attempts: None,
+        })
+        .build();
+
+    client.push(EventBuilder::new("event", 0).build());
+    runtime.tick();
+
+    // The backoff delay would be 10s but the event store asked for 30s
+    let info = client.info();
+    assert_eq!(info.consecutive_failures, 1);
+    assert_eq!(info.next_flush_scheduled, Some(30_000));
+
+    // Once the backoff delay exceeds `retry_after`, the backoff delay is used
+    runtime.inner().timestamp = 30_000;
+    runtime.tick();
+    runtime.tick();
+    runtime.inner().timestamp = 60_000;
+    runtime.tick();
+    runtime.tick();
+    let info = client.info();
+    assert_eq!(info.consecutive_failures, 3);
+    assert_eq!(info.next_flush_scheduled, Some(100_000));
+}
+
+#[test_case(OverflowPolicy::DropOldest, &["2", "3", "4"], 2, 0)]
+#[test_case(OverflowPolicy::DropNewest, &["0", "1", "2"], 2, 0)]
+#[test_case(OverflowPolicy::Reject, &["0", "1", "2"], 0, 2)]
+fn buffer_limited_by_event_count(
+    overflow_policy: OverflowPolicy,
+    expected: &[&str],
+    expected_dropped: u64,
+    expected_rejected: u64,
+) {
+    let runtime = TestRuntime::new(true);
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_buffered_events(3)
+        .with_overflow_policy(overflow_policy)
+        .build();
+
+    for i in 0..5 {
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+
+    let info = client.info();
+    assert_eq!(info.events_pending, 3);
+    assert_eq!(info.total_events_dropped, expected_dropped);
+    assert_eq!(info.total_events_rejected, expected_rejected);
+
+    let names: Vec<_> = client.take_events().into_iter().map(|e| e.name).collect();
+    assert_eq!(names, expected);
+}
+
+#[test]
+fn lowest_priority_events_dropped_first() {
+    let runtime = TestRuntime::new(true);
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_buffered_events(3)
+        .with_overflow_policy(OverflowPolicy::DropLowestPriority)
+        .with_event_priority("low", 0)
+        .with_event_priority("high", 200)
+        .build();
+
+    for name in ["high", "low", "normal", "low", "high", "normal"] {
+        client.push(EventBuilder::new(name, 0).build());
+    }
+
+    assert_eq!(client.info().total_events_dropped, 3);
+
+    let names: Vec<_> = client.take_events().into_iter().map(|e| e.name).collect();
+    assert_eq!(names, ["high", "high", "normal"]);
+}
+
+#[test]
+fn buffer_limited_by_bytes() {
+    let runtime = TestRuntime::new(true);
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_buffered_bytes(400)
+        .build();
+
+    for i in 0..5 {
+        client.push(
+            EventBuilder::new(i.to_string(), 0)
+                .with_payload(vec![0; 100])
+                .build(),
+        );
+    }
+
+    // Each event is estimated to be 149 bytes, so only 2 fit
+    let info = client.info();
+    assert_eq!(info.events_pending, 2);
+    assert_eq!(info.events_pending_bytes, 298);
+    assert_eq!(info.total_events_dropped, 3);
+}
+
+#[test]
+fn try_push_returns_error_if_busy_or_buffer_full() {
+    let runtime = TestRuntime::new(true);
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_buffered_events(1)
+        .with_overflow_policy(OverflowPolicy::Reject)
+        .build();
+
+    let clone = client.clone();
+    let guard = clone.inner.lock().unwrap();
+    assert_eq!(
+        client.try_push(EventBuilder::new("0", 0).build()),
+        Err(PushError::Busy)
+    );
+    drop(guard);
+
+    assert_eq!(client.try_push(EventBuilder::new("1", 0).build()), Ok(()));
+    assert_eq!(
+        client.try_push(EventBuilder::new("2", 0).build()),
+        Err(PushError::BufferFull)
+    );
+    assert_eq!(client.info().total_events_rejected, 1);
+}
+
+#[test]
+fn push_async_waits_until_not_busy() {
+    let runtime = TestRuntime::new(true);
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone()).build();
+    let mut clone = client.clone();
+    let mut future = pin!(clone.push_async(EventBuilder::new("event", 0).build()));
+    let woken = Arc::new(ThreadWaker::default());
+    let waker = Waker::from(woken.clone());
+    let mut context = Context::from_waker(&waker);
+
+    let guard = client.inner.lock().unwrap();
+    assert!(future.as_mut().poll(&mut context).is_pending());
+    thread::sleep(Duration::from_millis(10));
+    // The task isn't woken until the event has been pushed
+    assert!(!woken.is_woken());
+    drop(guard);
+
+    woken.wait();
+    assert_eq!(future.as_mut().poll(&mut context), Poll::Ready(Ok(())));
+    assert_eq!(client.take_events().len(), 1);
+}
+
+#[derive(Default)]
+struct ThreadWaker {
+    woken: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl ThreadWaker {
+    fn is_woken(&self) -> bool {
+        *self.woken.lock().unwrap()
+    }
+
+    fn wait(&self) {
+        let guard = self.woken.lock().unwrap();
+        drop(self.condvar.wait_while(guard, |woken| !*woken).unwrap());
+    }
+}
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        *self.woken.lock().unwrap() = true;
+        self.condvar.notify_all();
+    }
+}
+
+#[test]
+fn push_from_multiple_threads() {
+    let runtime = TestRuntime::new(false);
+    let client = ClientBuilder::new(Principal::anonymous(), runtime.clone()).build();
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let mut client = client.clone();
+            thread::spawn(move || {
+                for i in 0..100 {
+                    client.push(EventBuilder::new(i.to_string(), 0).build());
+                    client.info();
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(client.info().events_pending, 400);
+}
+
+#[test]
+fn flush_now_drains_all_batches() {
+    let runtime = TestRuntime::new(false);
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(5)
+        .build();
+
+    client.push_many(
+        (0..12).map(|i| EventBuilder::new(i.to_string(), 0).build()),
+        false,
+    );
+    client.flush_now();
+
+    for _ in 0..10 {
+        runtime.tick();
+    }
+
+    let info = client.info();
+    assert_eq!(runtime.inner().flush_invocations, 3);
+    assert_eq!(info.events_pending, 0);
+    assert_eq!(info.total_events_flushed, 12);
+}
+
+#[test]
+fn shutdown_delivers_buffered_events() {
+    let runtime = TestRuntime::new(true);
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(5)
+        .build();
+
+    client.push_many(
+        (0..12).map(|i| EventBuilder::new(i.to_string(), 0).build()),
+        false,
+    );
+    let report = client.shutdown(Duration::from_secs(5));
+
+    assert!(report.undelivered.is_empty());
+    assert_eq!(report.events_in_flight, 0);
+    assert_eq!(client.info().total_events_flushed, 12);
+}
+
+#[test]
+fn shutdown_reports_undelivered_events_once_timeout_expires() {
+    let runtime = TestRuntime::new(true);
+    runtime.inner().flush_outcome = failed_should_retry();
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_retry_policy(RetryPolicy {
+            initial_delay: Duration::from_secs(60),
+            ..RetryPolicy::default()
+        })
+        .build();
+
+    client.push_many(
+        (0..3).map(|i| EventBuilder::new(i.to_string(), 0).build()),
+        false,
+    );
+    let report = client.shutdown(Duration::from_millis(100));
+
+    assert_eq!(report.undelivered.len(), 3);
+    assert_eq!(
+        client.try_push(EventBuilder::new("event", 0).build()),
+        Err(PushError::ShutDown)
+    );
+    assert!(client.info().shut_down);
+}
+
+#[test]
+fn concurrent_batches_retried_independently() {
+    let runtime = TestRuntime::new(true);
+    runtime.inner().hold_flushes = true;
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(2)
+        .with_max_concurrent_batches(3)
+        .build();
+
+    for i in 0..8 {
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+
+    // Only 3 batches can be in flight, the remaining events wait for a free slot
+    let info = client.info();
+    assert_eq!(info.batches_in_flight, 3);
+    assert_eq!(info.events_in_flight, 6);
+    assert_eq!(info.events_pending, 2);
+
+    // The failed batch is re-queued ahead of the buffered events, but isn't retried until the
+    // backoff delay has passed or another batch succeeds
+    runtime.complete_flush(1, failed_should_retry());
+    let info = client.info();
+    assert_eq!(info.batches_in_flight, 2);
+    assert_eq!(info.events_pending, 4);
+
+    runtime.complete_flush(0, FlushOutcome::Success);
+    runtime.tick();
+    let info = client.info();
+    assert_eq!(info.batches_in_flight, 3);
+    assert_eq!(info.events_pending, 0);
+    assert_eq!(info.total_events_flushed, 2);
+    assert_eq!(
+        runtime.held_flush_event_names(),
+        [vec!["4", "5"], vec!["2", "3"], vec!["6", "7"]]
+    );
+}
+
+#[test]
+fn batches_limited_by_estimated_bytes() {
+    let runtime = TestRuntime::new(true);
+    runtime.inner().hold_flushes = true;
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_bytes(300)
+        .with_max_concurrent_batches(3)
+        .build();
+
+    // Each event is estimated at 149 bytes, so only 2 fit in a batch
+    for i in 0..5 {
+        client.push(
+            EventBuilder::new(i.to_string(), 0)
+                .with_payload(vec![0; 100])
+                .build(),
+        );
+    }
+
+    assert_eq!(
+        runtime.held_flush_event_names(),
+        [vec!["0", "1"], vec!["2", "3"]]
+    );
+    assert_eq!(client.info().events_pending, 1);
+}
+
+#[test]
+fn batches_rejected_as_too_large_are_split() {
+    let runtime = TestRuntime::new(true);
+    runtime.inner().hold_flushes = true;
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(4)
+        .with_max_concurrent_batches(2)
+        .build();
+
+    for i in 0..4 {
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+    runtime.complete_flush(0, failed_batch_too_large());
+    runtime.tick();
+
+    let info = client.info();
+    assert_eq!(info.batch_size_limit, Some(2));
+    assert_eq!(info.total_batches_split, 1);
+    assert_eq!(info.consecutive_failures, 0);
+    assert_eq!(
+        runtime.held_flush_event_names(),
+        [vec!["0", "1"], vec!["2", "3"]]
+    );
+
+    runtime.complete_flush(0, failed_batch_too_large());
+    runtime.complete_flush(0, FlushOutcome::Success);
+    runtime.tick();
+    assert_eq!(runtime.held_flush_event_names(), [vec!["0"], vec!["1"]]);
+
+    // A single event which is too large is abandoned rather than retried
+    runtime.complete_flush(0, failed_batch_too_large());
+    runtime.complete_flush(0, FlushOutcome::Success);
+    let info = client.info();
+    assert_eq!(info.total_events_abandoned, 1);
+    assert_eq!(info.total_events_flushed, 3);
+    assert_eq!(info.events_pending, 0);
+}
+
+#[test]
+fn batch_size_limit_uses_store_max_and_recovers() {
+    let runtime = TestRuntime::new(true);
+    runtime.inner().hold_flushes = true;
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(8)
+        .build();
+
+    for i in 0..8 {
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+    runtime.complete_flush(
+        0,
+        FlushOutcome::FailedBatchTooLarge(FlushError {
+            reject_code: None,
+            message: "Batch too large".to_string(),
+            retry_after: None,
+            max_batch_size: Some(3),
+        }),
+    );
+    runtime.tick();
+
+    assert_eq!(client.info().batch_size_limit, Some(3));
+    assert_eq!(runtime.held_flush_event_names(), [vec!["0", "1", "2"]]);
+
+    // Once enough flushes succeed the limit is doubled, then removed once it exceeds the max
+    let push_and_flush = |client: &mut EventStoreClient<TestRuntime>, range: Range<u32>| {
+        for i in range {
+            client.push(EventBuilder::new(i.to_string(), 0).build());
+            runtime.tick();
+            while !runtime.inner().held_flushes.is_empty() {
+                runtime.complete_flush(0, FlushOutcome::Success);
+                runtime.tick();
+            }
+        }
+    };
+    push_and_flush(&mut client, 8..32);
+    assert_eq!(client.info().total_events_flushed, 30);
+    assert_eq!(client.info().batch_size_limit, Some(6));
+
+    push_and_flush(&mut client, 32..90);
+    assert_eq!(client.info().batch_size_limit, None);
+}
+
+#[test]
+fn poison_events_isolated_by_bisection() {
+    let runtime = TestRuntime::new(true);
+    runtime.inner().hold_flushes = true;
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(4)
+        .with_bisect_after_failures(Some(2))
+        .with_retry_policy(RetryPolicy {
+            initial_delay: Duration::from_secs(1),
+            multiplier: 1.0,
+            max_delay: Duration::from_secs(1),
+            jitter: 0.0,
+            max_attempts: None,
+        })
+        .build();
+
+    for i in 0..4 {
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+
+    // Any batch containing event "2" causes the event store to trap
+    let complete_flush = |due_at| {
+        let poisoned = runtime.held_flush_event_names()[0].contains(&"2".to_string());
+        runtime.complete_flush(
+            0,
+            if poisoned {
+                FlushOutcome::from_reject(5, "Canister trapped".to_string())
+            } else {
+                FlushOutcome::Success
+            },
+        );
+        runtime.inner().timestamp = due_at;
+        runtime.tick();
+    };
+
+    complete_flush(1000);
+    assert_eq!(client.info().events_quarantined, 0);
+
+    // The second failure triggers bisection, the first half is then flushed on its own
+    complete_flush(2000);
+    assert_eq!(client.info().events_quarantined, 2);
+
+    for (due_at, expected_batch) in [
+        (2000, vec!["0", "1"]),
+        (3000, vec!["2", "3"]),
+        (4000, vec!["2"]),
+        (4000, vec!["3"]),
+    ] {
+        assert_eq!(runtime.held_flush_event_names(), [expected_batch]);
+        complete_flush(due_at);
+    }
+
+    let info = client.info();
+    assert_eq!(info.total_events_flushed, 3);
+    assert_eq!(info.events_pending, 0);
+    assert_eq!(info.events_quarantined, 0);
+    assert_eq!(info.dead_letters, 1);
+
+    let dead_letters = client.take_dead_letters();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].name, "2");
+    assert_eq!(client.info().dead_letters, 0);
+}
+
+#[test]
+fn oldest_dead_letters_dropped_once_limit_reached() {
+    let runtime = TestRuntime::new(true);
+    runtime.inner().hold_flushes = true;
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(4)
+        .with_bisect_after_failures(Some(1))
+        .with_max_dead_letters(1)
+        .with_retry_policy(RetryPolicy {
+            initial_delay: Duration::from_secs(1),
+            multiplier: 1.0,
+            max_delay: Duration::from_secs(1),
+            jitter: 0.0,
+            max_attempts: None,
+        })
+        .build();
+
+    for i in 0..4 {
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+
+    // Any batch containing event "1" or "2" causes the event store to trap
+    let mut now = 0;
+    while !runtime.inner().held_flushes.is_empty() {
+        let names = runtime.held_flush_event_names().remove(0);
+        let poisoned = names.iter().any(|name| name == "1" || name == "2");
+        runtime.complete_flush(
+            0,
+            if poisoned {
+                FlushOutcome::from_reject(5, "Canister trapped".to_string())
+            } else {
+                FlushOutcome::Success
+            },
+        );
+        now += 1000;
+        runtime.inner().timestamp = now;
+        runtime.tick();
+    }
+
+    let info = client.info();
+    assert_eq!(info.total_events_flushed, 2);
+    assert_eq!(info.total_events_dropped, 1);
+    assert_eq!(info.dead_letters, 1);
+    assert_eq!(client.take_dead_letters()[0].name, "2");
+}
+
+#[test]
+fn events_requeued_behind_quarantined_batches() {
+    let runtime = TestRuntime::new(true);
+    runtime.inner().hold_flushes = true;
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(2)
+        .with_max_concurrent_batches(2)
+        .with_bisect_after_failures(Some(1))
+        .with_retry_policy(RetryPolicy {
+            initial_delay: Duration::from_secs(1),
+            multiplier: 1.0,
+            max_delay: Duration::from_secs(1),
+            jitter: 0.0,
+            max_attempts: None,
+        })
+        .build();
+
+    for i in 0..4 {
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+    assert_eq!(
+        runtime.held_flush_event_names(),
+        [vec!["0", "1"], vec!["2", "3"]]
+    );
+
+    // The first batch is bisected, then the second fails for a reason unrelated to its events
+    runtime.complete_flush(
+        0,
+        FlushOutcome::from_reject(5, "Canister trapped".to_string()),
+    );
+    runtime.complete_flush(0, failed_should_retry());
+    assert_eq!(client.info().events_quarantined, 2);
+
+    // The quarantined events are still the ones flushed in isolation
+    runtime.inner().timestamp = 1000;
+    runtime.tick();
+    assert_eq!(runtime.held_flush_event_names(), [vec!["0"]]);
+
+    runtime.complete_flush(0, FlushOutcome::Success);
+    runtime.tick();
+    assert_eq!(
+        runtime.held_flush_event_names(),
+        [vec!["1"], vec!["2", "3"]]
+    );
+    runtime.complete_flush(0, FlushOutcome::Success);
+    runtime.complete_flush(0, FlushOutcome::Success);
+    assert_eq!(client.info().total_events_flushed, 4);
+    assert_eq!(client.info().dead_letters, 0);
+}
+
+#[test]
+fn rate_limited_batches_not_bisected() {
+    let runtime = TestRuntime::new(true);
+    runtime.inner().hold_flushes = true;
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(2)
+        .with_bisect_after_failures(Some(1))
+        .with_retry_policy(RetryPolicy {
+            initial_delay: Duration::from_secs(1),
+            multiplier: 1.0,
+            max_delay: Duration::from_secs(1),
+            jitter: 0.0,
+            max_attempts: None,
+        })
+        .build();
+    let rate_limited = FlushOutcome::FailedShouldRetry(FlushError {
+        reject_code: None,
+        message: "Rate limit exceeded".to_string(),
+        retry_after: Some(Duration::from_secs(1)),
+        max_batch_size: None,
+    });
+
+    for i in 0..4 {
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+
+    for (due_at, outcome) in [
+        (1000, rate_limited.clone()),
+        (
+            2000,
+            FlushOutcome::from_reject(2, "Unreachable".to_string()),
+        ),
+        (2000, FlushOutcome::Success),
+        (3000, rate_limited),
+    ] {
+        assert_eq!(runtime.held_flush_event_names()[0].len(), 2);
+        runtime.complete_flush(0, outcome);
+        assert_eq!(client.info().events_quarantined, 0);
+        runtime.inner().timestamp = due_at;
+        runtime.tick();
+    }
+
+    // The rate limited batch is retried whole rather than being split or dead lettered
+    assert_eq!(runtime.held_flush_event_names(), [vec!["2", "3"]]);
+    let info = client.info();
+    assert_eq!(info.total_events_flushed, 2);
+    assert_eq!(info.dead_letters, 0);
+}
+
+#[test_case(1, false)]
+#[test_case(2, true)]
+#[test_case(3, false)]
+#[test_case(4, false)]
+#[test_case(5, true)]
+#[test_case(6, true)]
+fn rejects_classified_by_reject_code(reject_code: u32, should_retry: bool) {
+    let outcome = FlushOutcome::from_reject(reject_code, "rejected".to_string());
+
+    assert_eq!(
+        matches!(outcome, FlushOutcome::FailedShouldRetry(_)),
+        should_retry
+    );
+    assert_eq!(outcome.error().unwrap().reject_code, Some(reject_code));
+}
+
+#[test]
+fn last_flush_error_surfaced_in_info() {
+    let runtime = TestRuntime::new(true);
+    runtime.inner().timestamp = 1000;
+    runtime.inner().flush_outcome =
+        FlushOutcome::from_reject(4, "Caller not authorized".to_string());
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_max_batch_size(2)
+        .build();
+
+    for i in 0..2 {
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+    thread::sleep(Duration::from_millis(10));
+
+    // Retrying wouldn't help, so the batch is dropped rather than re-queued
+    let info = client.info();
+    assert_eq!(info.events_pending, 0);
+    assert_eq!(info.consecutive_failures, 0);
+    assert_eq!(info.total_events_flushed, 0);
+    assert_eq!(info.total_events_rejected_by_store, 2);
+    assert_eq!(
+        info.last_flush_error,
+        Some(FlushError {
+            reject_code: Some(4),
+            message: "Caller not authorized".to_string(),
+            retry_after: None,
+            max_batch_size: None,
+        })
+    );
+    assert_eq!(info.last_flush_error_at, Some(1000));
+
+    // A later successful flush doesn't clear the last error
+    runtime.inner().flush_outcome = FlushOutcome::Success;
+    for i in 2..4 {
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+    thread::sleep(Duration::from_millis(10));
+
+    let info = client.info();
+    assert_eq!(info.total_events_flushed, 2);
+    assert!(info.last_flush_error.is_some());
+}
+
+#[test]
+fn spooled_events_recovered_after_restart() {
+    let spool = TestSpool::default();
+    let runtime = TestRuntime::new(true);
+    runtime.inner().flush_outcome = failed_should_retry();
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_spool(spool.clone())
+        .build();
+
+    for i in 0..3 {
+        runtime.inner().rng = i;
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+    let report = client.shutdown(Duration::from_millis(100));
+
+    // Undelivered events are left in the spool
+    assert_eq!(report.undelivered.len(), 3);
+    assert_eq!(spool.keys(), vec![0, 1, 2]);
+
+    let runtime = TestRuntime::new(true);
+    let client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_flush_delay(Duration::from_secs(5))
+        .with_spool(spool.clone())
+        .build();
+
+    assert_eq!(client.info().events_pending, 3);
+
+    runtime.inner().timestamp += 5000;
+    runtime.tick();
+    thread::sleep(Duration::from_millis(10));
+
+    assert_eq!(runtime.inner().flush_invocations, 1);
+    assert_eq!(client.info().total_events_flushed, 3);
+    assert!(spool.keys().is_empty());
+}
+
+#[test]
+fn events_failing_after_shutdown_left_in_spool() {
+    let spool = TestSpool::default();
+    let runtime = TestRuntime::new(true);
+    runtime.inner().hold_flushes = true;
+    let mut client = ClientBuilder::new(Principal::anonymous(), runtime.clone())
+        .with_spool(spool.clone())
+        .build();
+
+    for i in 0..3 {
+        runtime.inner().rng = i;
+        client.push(EventBuilder::new(i.to_string(), 0).build());
+    }
+    let report = client.shutdown(Duration::from_millis(10));
+
+    assert!(report.undelivered.is_empty());
+    assert_eq!(report.events_in_flight, 3);
+
+    runtime.complete_flush(0, failed_should_retry());
+
+    // The events can't be retried by this client but will be recovered by the next one
+    assert_eq!(client.info().total_events_abandoned, 3);
+    assert_eq!(spool.keys(), vec![0, 1, 2]);
+}
+
+#[derive(Default, Clone)]
+struct TestSpool {
+    events: Arc<Mutex<Vec<IdempotentEvent>>>,
+}
+
+impl TestSpool {
+    fn keys(&self) -> Vec<u128> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.idempotency_key)
+            .collect()
+    }
+}
+
+impl Spool for TestSpool {
+    fn append(&mut self, events: &[IdempotentEvent]) -> std::io::Result<()> {
+        self.events.lock().unwrap().extend_from_slice(events);
+        Ok(())
+    }
+
+    fn acknowledge(&mut self, idempotency_keys: &[u128]) -> std::io::Result<()> {
+        self.events
+            .lock()
+            .unwrap()
+            .retain(|e| !idempotency_keys.contains(&e.idempotency_key));
+        Ok(())
+    }
+
+    fn recover(&mut self) -> std::io::Result<Vec<IdempotentEvent>> {
+        Ok(self.events.lock().unwrap().clone())
+    }
+}
+
 #[derive(Default, Clone)]
 struct TestRuntime {
     inner: Arc<Mutex<TestRuntimeInner>>,
@@ -71,6 +963,11 @@ impl TestRuntime {
     }
 }
 
+type HeldFlush = (
+    Vec<IdempotentEvent>,
+    Box<dyn FnOnce(FlushOutcome) + Send + 'static>,
+);
+
 #[derive(Default)]
 struct TestRuntimeInner {
     timestamp: TimestampMillis,
@@ -80,6 +977,10 @@ struct TestRuntimeInner {
     callback_due_at: Option<TimestampMillis>,
     callback: Option<Box<dyn FnOnce() + Send + 'static>>,
     flush_invocations: u32,
+    flushed_streams: Vec<Option<String>>,
+    // If set, flushes don't complete until `complete_flush` is called
+    hold_flushes: bool,
+    held_flushes: Vec<HeldFlush>,
     rng_invocations: u32,
     now_invocations: u32,
 }
@@ -95,14 +996,18 @@ impl Runtime for TestRuntime {
     fn flush<F: FnOnce(FlushOutcome) + Send + 'static>(
         &mut self,
         _event_store_canister_id: Principal,
-        _events: Vec<IdempotentEvent>,
+        stream: Option<String>,
+        events: Vec<IdempotentEvent>,
         on_complete: F,
     ) {
         let mut guard = self.inner();
         guard.flush_invocations += 1;
-        let outcome = guard.flush_outcome;
+        guard.flushed_streams.push(stream);
+        let outcome = guard.flush_outcome.clone();
 
-        if self.flush_synchronously {
+        if guard.hold_flushes {
+            guard.held_flushes.push((events, Box::new(on_complete)));
+        } else if self.flush_synchronously {
             guard.callback_due_at = None;
             guard.callback = None;
             on_complete(outcome);
@@ -132,6 +1037,19 @@ impl TestRuntime {
         }
     }
 
+    fn complete_flush(&self, index: usize, outcome: FlushOutcome) {
+        let (_, on_complete) = self.inner().held_flushes.remove(index);
+        on_complete(outcome);
+    }
+
+    fn held_flush_event_names(&self) -> Vec<Vec<String>> {
+        self.inner()
+            .held_flushes
+            .iter()
+            .map(|(events, _)| events.iter().map(|e| e.name.clone()).collect())
+            .collect()
+    }
+
     fn take_callback_if_due(&self) -> Option<Box<dyn FnOnce() + Send + 'static>> {
         let mut guard = self.inner();
         guard
@@ -140,3 +1058,16 @@ impl TestRuntime {
             .and_then(|_| guard.callback.take())
     }
 }
+
+fn failed_should_retry() -> FlushOutcome {
+    FlushOutcome::from_reject(2, "Event store unavailable".to_string())
+}
+
+fn failed_batch_too_large() -> FlushOutcome {
+    FlushOutcome::FailedBatchTooLarge(FlushError {
+        reject_code: None,
+        message: "Batch too large".to_string(),
+        retry_after: None,
+        max_batch_size: None,
+    })
+}
@@ -89,6 +89,12 @@ pub struct IndexedEvent {
     pub index: u64,
     pub name: String,
     pub timestamp: TimestampMillis,
+    // When the event store received the event. This is `None` for events which were stored before
+    // ingestion timestamps were recorded.
+    pub ingested_at: Option<TimestampMillis>,
+    // If the event's name was being sampled when it was stored, this is the proportion of events
+    // which were kept, in parts per million. Counts should be scaled up accordingly.
+    pub sample_rate_per_million: Option<u32>,
     pub user: Option<String>,
     pub source: Option<String>,
     #[serde(with = "serde_bytes")]
@@ -35,6 +35,10 @@ impl EventDeduper {
         }
     }
 
+    pub fn window_duration(&self) -> Milliseconds {
+        self.window_duration
+    }
+
     pub fn is_empty(&self) -> bool {
         self.recently_added.is_empty()
     }